@@ -0,0 +1,17 @@
+use thepipelinetool_core::{prelude::*, tpt};
+
+fn extract(_: ()) -> Vec<u32> {
+    vec![1, 2, 3]
+}
+
+fn transform(rows: Vec<u32>) -> u32 {
+    rows.iter().sum()
+}
+
+// extended with bash steps by examples/yaml/hybrid.yaml
+#[tpt::main]
+fn main() {
+    let opts = TaskOptions::default();
+    let rows = add_task(extract, (), &opts);
+    let _ = add_task_with_ref(transform, &rows, &opts);
+}
@@ -0,0 +1,19 @@
+use thepipelinetool_core::{prelude::*, tpt};
+
+fn load_partition(_: ()) -> String {
+    let context = current_context();
+    println!(
+        "run {} of {}, attempt {}",
+        context.run_id, context.pipeline_name, context.attempt
+    );
+
+    format!(
+        "SELECT * FROM events WHERE ts >= '{}' AND ts < '{}'",
+        context.data_interval_start, context.data_interval_end
+    )
+}
+
+#[tpt::main]
+fn main() {
+    let _ = add_task(load_partition, (), &TaskOptions::default());
+}
@@ -0,0 +1,33 @@
+use thepipelinetool_core::{prelude::*, tpt};
+
+fn choose_format(path: String) -> Switch<String> {
+    if path.ends_with(".csv") {
+        Switch::new("load_csv", path)
+    } else if path.ends_with(".json") {
+        Switch::new("load_json", path)
+    } else {
+        Switch::new("reject", path)
+    }
+}
+
+fn load_csv(path: String) {
+    println!("loading csv {path}");
+}
+
+fn load_json(path: String) {
+    println!("loading json {path}");
+}
+
+fn reject(path: String) {
+    println!("unsupported file {path}");
+}
+
+#[tpt::main]
+fn main() {
+    let opts = TaskOptions::default();
+    let path = switch(choose_format, "data.json".to_string(), &opts);
+
+    let _ = add_arm(&path, "load_csv", load_csv, &opts);
+    let _ = add_arm(&path, "load_json", load_json, &opts);
+    let _ = add_arm(&path, "reject", reject, &opts);
+}
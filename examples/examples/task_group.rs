@@ -0,0 +1,40 @@
+use thepipelinetool_core::{prelude::*, tpt};
+
+fn start(_: ()) {
+    println!("start");
+}
+
+fn extract(source: String) -> Vec<u32> {
+    println!("extracting {source}");
+    vec![1, 2, 3]
+}
+
+fn transform(rows: Vec<u32>) -> u32 {
+    rows.iter().sum()
+}
+
+fn report(_: ()) {
+    println!("report");
+}
+
+// a reusable sub-pipeline, namespaced by the task group it's added in
+fn etl(source: &str) {
+    let opts = TaskOptions::default();
+    let rows = add_task(extract, source.to_string(), &opts);
+    let _ = add_task_with_ref(transform, &rows, &opts);
+}
+
+#[tpt::main]
+fn main() {
+    let opts = TaskOptions::default();
+    let start = add_task(start, (), &opts);
+
+    // tasks are named like 'orders/extract' and 'users/transform'
+    let orders = task_group("orders", || etl("orders.csv"));
+    let users = task_group("users", || etl("users.csv"));
+
+    let report = add_task(report, (), &opts);
+
+    let _ = &start >> &orders >> &report;
+    let _ = &start >> &users >> &report;
+}
@@ -1,14 +1,19 @@
 use std::{
     env,
     fs::File,
+    path::{Path, PathBuf},
     process::{self, Command},
+    time::Duration,
 };
 
 use anyhow::Result;
 use clap::Arg;
 use thepipelinetool::{
-    commands::create_commands, process_subcommands, read_from_executable::read_from_executable,
-    read_from_yaml::read_from_yaml, source_type::SourceType,
+    commands::create_commands,
+    process_subcommands,
+    read_from_executable::read_from_executable,
+    read_from_yaml::{get_executable, read_from_yaml},
+    source_type::SourceType,
 };
 use thepipelinetool_core::dev::{
     assert::assert_operator, params::params_operator, print::print_operator,
@@ -26,14 +31,21 @@ fn main() -> Result<()> {
 
     let subcommand_name = matches.subcommand_name().unwrap();
 
+    let is_run_function = args.len() > 4 && args[2..4] == ["run", "function"];
+    let mut executable: Option<PathBuf> = None;
+
     match source_type {
+        // built-in operators used by the yaml tasks of a hybrid pipeline run in tpt itself
+        SourceType::Exe if is_run_function && register_built_in_operator(&args[4]) => {}
         SourceType::Exe => {
-            if args.len() > 4 && args[2..4] == ["run", "function"] {
+            if is_run_function {
                 let mut cmd = Command::new(pipeline_source.unwrap());
                 cmd.args(&mut args[2..]);
-                let exit_status = spawn(
+                let (exit_status, _) = spawn(
                     cmd,
                     None,
+                    Duration::ZERO,
+                    None,
                     Box::new(|x| {
                         print!("{x}");
                         Ok(())
@@ -43,46 +55,48 @@ fn main() -> Result<()> {
                         Ok(())
                     }),
                 )?;
-                process::exit(exit_status.code().unwrap());
+                process::exit(get_exit_code(&exit_status).unwrap());
             } else {
                 read_from_executable(pipeline_source.unwrap())
             }
         }
-        SourceType::Yaml => {
-            read_from_yaml(serde_yaml::from_reader(File::open(
-                pipeline_source.unwrap(),
-            )?)?);
-        }
-        SourceType::Raw => {
-            read_from_yaml(serde_json::from_str(pipeline_source.unwrap())?);
+        SourceType::Yaml | SourceType::Raw => {
+            let (value, base_dir): (Value, &Path) = match source_type {
+                SourceType::Yaml => {
+                    let path = Path::new(pipeline_source.unwrap());
+                    (
+                        serde_yaml::from_reader(File::open(path)?)?,
+                        path.parent().unwrap_or(Path::new(".")),
+                    )
+                }
+                _ => (
+                    serde_json::from_str(pipeline_source.unwrap())?,
+                    Path::new("."),
+                ),
+            };
+
+            // tasks of the executable are added first, so yaml tasks can depend on them
+            executable = get_executable(&value, base_dir)?;
+            if let Some(executable) = &executable {
+                read_from_executable(executable.to_str().unwrap());
+            }
+            read_from_yaml(value, base_dir)?;
         }
         SourceType::None => {
-            // try parse operator
-            let operator = &serde_json::from_value::<Operator>(json!(args[4])).ok();
-            // register built-in operators if used
-            if let Some(built_in_operator) = operator {
-                _register_function_with_name(
-                    match built_in_operator {
-                        Operator::BashOperator => bash_operator,
-                        Operator::ParamsOperator => params_operator,
-                        Operator::PrintOperator => print_operator,
-                        Operator::AssertOperator => assert_operator,
-                        Operator::PythonOperator => python_operator,
-                    },
-                    &args[4],
-                );
-            } else if args[4] == "collector" {
-                register_function(collector);
-            } else {
-                panic!(
-                    "no such function '{}'\navailable functions: {:#?}",
-                    &args[4],
-                    get_functions()
-                        .read()
-                        .unwrap()
-                        .keys()
-                        .collect::<Vec<&String>>()
-                );
+            if !register_built_in_operator(&args[4]) {
+                if args[4] == "collector" {
+                    register_function(collector);
+                } else {
+                    panic!(
+                        "no such function '{}'\navailable functions: {:#?}",
+                        &args[4],
+                        get_functions()
+                            .read()
+                            .unwrap()
+                            .keys()
+                            .collect::<Vec<&String>>()
+                    );
+                }
             }
 
             // for built_in_operator in vec![
@@ -114,11 +128,30 @@ fn main() -> Result<()> {
 
     let pipeline_path = match source_type {
         SourceType::Exe => pipeline_source.unwrap(),
-        SourceType::Yaml => "",
-        SourceType::Raw => "",
+        SourceType::Yaml | SourceType::Raw => executable
+            .as_ref()
+            .map_or("", |executable| executable.to_str().unwrap()),
         SourceType::None => "",
     };
 
     process_subcommands(pipeline_path, subcommand_name, &options, &matches)?;
     Ok(())
 }
+
+fn register_built_in_operator(function_name: &str) -> bool {
+    // try parse operator
+    let Ok(built_in_operator) = serde_json::from_value::<Operator>(json!(function_name)) else {
+        return false;
+    };
+    _register_function_with_name(
+        match built_in_operator {
+            Operator::BashOperator => bash_operator,
+            Operator::ParamsOperator => params_operator,
+            Operator::PrintOperator => print_operator,
+            Operator::AssertOperator => assert_operator,
+            Operator::PythonOperator => python_operator,
+        },
+        function_name,
+    );
+    true
+}
@@ -84,5 +84,27 @@ pub fn create_commands() -> CliCommand {
                 .arg(Arg::new("endpoint"))
                 .arg_required_else_help(true),
         )
+        .subcommand(
+            CliCommand::new("clear")
+                .about("Clear a task in a server run and run it again")
+                .arg_required_else_help(true)
+                .arg(
+                    arg!(
+                        <endpoint> "Clear endpoint of the pipeline, e.g. http://localhost:8000/clear/my_pipeline"
+                    )
+                    .required(true),
+                )
+                .arg(
+                    arg!(<run_id> "Run id")
+                        .required(true)
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    arg!(<task_id> "Task id")
+                        .required(true)
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(arg!(--downstream "Also clear all downstream tasks")),
+        )
         .subcommand_required(true)
 }
@@ -1,43 +1,20 @@
-use std::{sync::mpsc::channel, thread};
+use std::{sync::mpsc::channel, thread, time::Duration};
 
 use thepipelinetool_runner::{
     backend::Backend, blanket_backend::BlanketBackend, in_memory_backend::InMemoryBackend,
 };
 
+const QUEUE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 pub fn run_in_memory(backend: &mut InMemoryBackend, max_parallelism: usize, tpt_path: String) {
     let (tx, rx) = channel();
     let mut current_parallel_tasks_count = 0;
 
-    for _ in 0..max_parallelism {
-        if let Some(temp_queued_task) = backend.pop_priority_queue().unwrap() {
-            let tx = tx.clone();
-            let mut backend = backend.clone();
-            let tpt_path = tpt_path.clone();
-
-            thread::spawn(move || {
-                backend.work(&temp_queued_task, tpt_path).unwrap();
-                backend.remove_from_temp_queue(&temp_queued_task).unwrap();
-                tx.send(()).unwrap();
-            });
-
-            current_parallel_tasks_count += 1;
-            if current_parallel_tasks_count >= max_parallelism {
+    loop {
+        while current_parallel_tasks_count < max_parallelism {
+            let Some(temp_queued_task) = backend.pop_priority_queue().unwrap() else {
                 break;
-            }
-        } else {
-            break;
-        }
-    }
-
-    if current_parallel_tasks_count == 0 {
-        drop(tx);
-        return;
-    }
-
-    for _ in rx.iter() {
-        current_parallel_tasks_count -= 1;
-
-        if let Some(temp_queued_task) = backend.pop_priority_queue().unwrap() {
+            };
             let tx = tx.clone();
             let mut backend = backend.clone();
             let tpt_path = tpt_path.clone();
@@ -48,14 +25,15 @@ pub fn run_in_memory(backend: &mut InMemoryBackend, max_parallelism: usize, tpt_
                 tx.send(()).unwrap();
             });
             current_parallel_tasks_count += 1;
-
-            if current_parallel_tasks_count >= max_parallelism {
-                continue;
-            }
         }
-        if current_parallel_tasks_count == 0 {
-            drop(tx);
+
+        if current_parallel_tasks_count == 0 && backend.get_queue_length().unwrap() == 0 {
             break;
         }
+
+        // wake up when a task finishes, or to pop delayed tasks once they are due
+        if rx.recv_timeout(QUEUE_POLL_INTERVAL).is_ok() {
+            current_parallel_tasks_count -= 1 + rx.try_iter().count();
+        }
     }
 }
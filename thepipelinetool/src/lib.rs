@@ -111,7 +111,8 @@ pub fn process_subcommands(
 
                     check_for_cycles(tasks, edges);
 
-                    let mut backend = InMemoryBackend::new(pipeline_path, tasks, edges);
+                    let mut backend =
+                        InMemoryBackend::new(pipeline_path, tasks, edges).with_options(options);
                     let run = Run::dummy();
                     backend.enqueue_run(&run, trigger_params)?;
 
@@ -155,6 +156,28 @@ pub fn process_subcommands(
 
             // dbg!(pipeline);
         }
+        "clear" => {
+            let matches = matches.subcommand_matches("clear").unwrap();
+            let endpoint = matches.get_one::<String>("endpoint").expect("required");
+            let run_id = matches.get_one::<usize>("run_id").expect("required");
+            let task_id = matches.get_one::<usize>("task_id").expect("required");
+            let downstream = matches.get_flag("downstream");
+
+            let client = reqwest::blocking::Client::new();
+            let res = client
+                .post(format!(
+                    "{}/{run_id}/{task_id}?downstream={downstream}",
+                    endpoint.trim_end_matches('/')
+                ))
+                .send()?;
+            if !res.status().is_success() {
+                eprintln!(
+                    "clear failed\n{}",
+                    res.text().expect("server should return error msg")
+                );
+                process::exit(1);
+            }
+        }
         _ => {}
     };
     Ok(())
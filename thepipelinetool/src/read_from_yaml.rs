@@ -1,31 +1,232 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    path::{Path, PathBuf},
+};
 
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
 use serde_json::{json, Value};
 use thepipelinetool_core::dev::{
-    get_edges, get_tasks, Operator, _add_task_with_function_name, _expand_lazy_with_function_name,
-    _lazy_task_ref,
+    _add_task_with_function_name, _expand_lazy_with_function_name, _lazy_task_ref, _task_ref,
+    get_edges, get_id_by_task_name, get_tasks, task_group, Operator, TaskRef,
+};
+
+use crate::templating::{
+    create_edges_from_condition, create_template_args_by_operator, get_upstream_id, TemplateTask,
 };
 
-use crate::templating::{create_template_args_by_operator, TemplateTask};
+// pipeline options that act as defaults for the options of every task
+const DEFAULT_TASK_OPTIONS_KEYS: [&str; 4] = [
+    "max_attempts",
+    "retry_delay",
+    "retry_policy",
+    "retry_jitter",
+];
+
+fn with_default_task_options(task: &Value, pipeline: &Value) -> Value {
+    let mut task = task.clone();
+    if !task.is_object() {
+        return task;
+    }
+
+    let mut options = task["options"].as_object().cloned().unwrap_or_default();
+    for key in DEFAULT_TASK_OPTIONS_KEYS {
+        if let Some(default) = pipeline.get(key) {
+            options.entry(key).or_insert(default.clone());
+        }
+    }
+    if !options.is_empty() {
+        task["options"] = options.into();
+    }
+    task
+}
+
+// a task entry with a `pipeline` key includes the tasks of that pipeline file as a task group
+const SUB_PIPELINE_KEY: &str = "pipeline";
+
+// top-level lists of pipeline files whose tasks are added to this pipeline
+const INCLUDE_KEYS: [&str; 2] = ["include", "imports"];
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Include {
+    Path(String),
+    WithOptions {
+        path: String,
+        #[serde(default)]
+        prefix: Option<String>,
+        #[serde(default)]
+        depends_on: Vec<String>,
+    },
+}
+
+// a top-level `executable` is a Rust pipeline whose tasks the tasks of this pipeline can use
+const EXECUTABLE_KEY: &str = "executable";
+
+/// The Rust pipeline executable a pipeline definition extends, if any.
+pub fn get_executable(value: &Value, base_dir: &Path) -> Result<Option<PathBuf>> {
+    match value.get(EXECUTABLE_KEY) {
+        None => Ok(None),
+        // relative to the current directory rather than searched for in PATH when run
+        Some(Value::String(path)) => Ok(Some(Path::new(".").join(base_dir).join(path))),
+        Some(_) => bail!("'{EXECUTABLE_KEY}' must be a path"),
+    }
+}
+
+fn read_pipeline_file(path: &Path) -> Result<Value> {
+    let file =
+        File::open(path).with_context(|| format!("error opening pipeline '{}'", path.display()))?;
+    serde_yaml::from_reader(file)
+        .with_context(|| format!("error parsing pipeline '{}'", path.display()))
+}
+
+fn read_sub_pipeline(
+    name: &str,
+    path: &str,
+    base_dir: &Path,
+) -> Result<(TaskRef<Value>, HashMap<String, usize>)> {
+    let path = base_dir.join(path);
+    let value = read_pipeline_file(&path)?;
+
+    let mut task_id_by_name = Ok(HashMap::new());
+    let task_group = task_group(name, || {
+        task_id_by_name = read_from_yaml(value, path.parent().unwrap_or(Path::new(".")));
+    });
+    let task_id_by_name = task_id_by_name?
+        .into_iter()
+        .map(|(task_name, id)| (format!("{name}/{task_name}"), id))
+        .collect();
+    Ok((task_group, task_id_by_name))
+}
+
+fn insert_task_name(
+    task_id_by_name: &mut HashMap<String, usize>,
+    name: String,
+    id: usize,
+) -> Result<()> {
+    if task_id_by_name.contains_key(&name) {
+        bail!("task '{name}' is defined more than once, rename it or include it with a prefix");
+    }
+    task_id_by_name.insert(name, id);
+    Ok(())
+}
+
+// included tasks by name, and the included tasks waiting on each list of upstream tasks
+type Includes = (HashMap<String, usize>, Vec<(HashSet<usize>, Vec<String>)>);
+
+fn read_includes(value: &Value, base_dir: &Path) -> Result<Includes> {
+    let mut task_id_by_name = HashMap::new();
+    let mut dependencies = vec![];
+
+    for key in INCLUDE_KEYS {
+        let Some(includes) = value.get(key) else {
+            continue;
+        };
+        let includes: Vec<Include> = serde_json::from_value(includes.clone())
+            .with_context(|| format!("error parsing '{key}'"))?;
+
+        for include in includes {
+            let (path, prefix, depends_on) = match include {
+                Include::Path(path) => (path, None, vec![]),
+                Include::WithOptions {
+                    path,
+                    prefix,
+                    depends_on,
+                } => (path, prefix, depends_on),
+            };
+            let imported = match prefix {
+                Some(prefix) => read_sub_pipeline(&prefix, &path, base_dir)?.1,
+                None => {
+                    let path = base_dir.join(path);
+                    read_from_yaml(
+                        read_pipeline_file(&path)?,
+                        path.parent().unwrap_or(Path::new(".")),
+                    )?
+                }
+            };
+            dependencies.push((imported.values().copied().collect(), depends_on));
+            for (name, id) in imported {
+                insert_task_name(&mut task_id_by_name, name, id)?;
+            }
+        }
+    }
+    Ok((task_id_by_name, dependencies))
+}
+
+// an entry with only `depends_on`, named after a task of the executable, adds upstream tasks to it
+fn is_executable_task_entry(name: &str, task: &Value) -> bool {
+    task.as_object()
+        .is_some_and(|task| task.keys().all(|key| key == "depends_on"))
+        && get_id_by_task_name(name).is_some()
+}
+
+/// Adds the tasks of a pipeline definition, returning their ids by name.
+///
+/// Paths of included pipelines are relative to `base_dir`. Tasks can depend on tasks added
+/// before, like the tasks of the pipeline's executable, by name.
+pub fn read_from_yaml(value: Value, base_dir: &Path) -> Result<HashMap<String, usize>> {
+    // included tasks are added first, and can be referenced like the tasks of this pipeline
+    let (mut task_id_by_name, include_dependencies) = read_includes(&value, base_dir)?;
 
-pub fn read_from_yaml(value: Value) {
     if value.as_object().unwrap().contains_key("tasks") {
-        let tasks = value["tasks"].as_object().unwrap();
-        let mut task_id_by_name: HashMap<String, usize> = HashMap::new();
+        let tasks = value["tasks"]
+            .as_object()
+            .ok_or_else(|| anyhow!("'tasks' must be a map of task names to tasks"))?;
+
+        let (executable_task_entries, tasks): (Vec<(&String, &Value)>, Vec<_>) = tasks
+            .iter()
+            .partition(|(name, task)| is_executable_task_entry(name, task));
+
+        // sub-pipelines are added first, so the ids of the remaining tasks follow them
+        let mut task_groups: Vec<(String, TaskRef<Value>, Vec<String>)> = vec![];
+        for &(name, v) in tasks.iter().rev() {
+            if let Some(path) = v.get(SUB_PIPELINE_KEY) {
+                let path = path
+                    .as_str()
+                    .ok_or_else(|| anyhow!("pipeline of task group '{name}' must be a path"))?;
+                let depends_on: Vec<String> =
+                    serde_json::from_value(v["depends_on"].clone()).unwrap_or_default();
+                let (task_group, group_task_id_by_name) =
+                    read_sub_pipeline(name, path, base_dir)
+                        .with_context(|| format!("error reading task group '{name}'"))?;
+                for (task_name, id) in group_task_id_by_name {
+                    insert_task_name(&mut task_id_by_name, task_name, id)?;
+                }
+                task_groups.push((name.to_string(), task_group, depends_on));
+            }
+        }
         let base_id = get_tasks().read().unwrap().len();
 
         let mut template_tasks: Vec<(TemplateTask, Value)> = tasks
             .iter()
             .rev()
+            .filter(|(_, v)| v.get(SUB_PIPELINE_KEY).is_none())
             .enumerate()
-            .map(|(i, (k, v))| {
-                task_id_by_name.insert(k.to_string(), base_id + i);
+            .map(|(i, &(k, v))| {
+                insert_task_name(&mut task_id_by_name, k.to_string(), base_id + i)?;
 
-                let mut template: TemplateTask = serde_json::from_value(v.clone()).unwrap();
+                let mut template: TemplateTask =
+                    serde_json::from_value(with_default_task_options(v, &value))
+                        .with_context(|| format!("error parsing task '{k}'"))?;
                 template.name = k.to_string();
-                (template, v.clone())
+                Ok((template, v.clone()))
             })
-            .collect();
+            .collect::<Result<_>>()?;
+
+        // the output of a branch task selects which of the tasks depending on it run
+        for (template_task, _) in &template_tasks {
+            if template_task.is_branch
+                && !template_tasks
+                    .iter()
+                    .any(|(t, _)| t.depends_on.contains(&template_task.name))
+            {
+                bail!(
+                    "branch task '{}' has no tasks depending on it",
+                    template_task.name
+                );
+            }
+        }
 
         for (template_task, value) in template_tasks.iter_mut() {
             let id = *task_id_by_name.get(&template_task.name).unwrap();
@@ -33,18 +234,26 @@ pub fn read_from_yaml(value: Value) {
                 value.is_object() && value["use_trigger_params"].as_bool().unwrap_or(false);
 
             // create edges
-            let depends_on: Vec<usize> = template_task
-                .depends_on
-                .iter()
-                .map(|dependency| {
-                    // TODO be able to depend on tasks defined in rust pipeline?
-                    let upstream_id = *task_id_by_name
-                        .get(dependency)
-                        .unwrap_or_else(|| panic!("upstream task '{dependency}' missing"));
-                    get_edges().write().unwrap().insert((upstream_id, id));
-                    upstream_id
-                })
-                .collect();
+            let mut depends_on: Vec<usize> = vec![];
+            for dependency in &template_task.depends_on {
+                // depending on a task group waits for all of its tasks
+                if let Some((_, group, _)) = task_groups.iter().find(|(n, _, _)| n == dependency) {
+                    let _ = group >> _task_ref(HashSet::from([id]));
+                    continue;
+                }
+                let upstream_id =
+                    get_upstream_id(dependency, &task_id_by_name).with_context(|| {
+                        format!("upstream task of task '{}' missing", template_task.name)
+                    })?;
+                get_edges().write().unwrap().insert((upstream_id, id));
+                depends_on.push(upstream_id);
+            }
+
+            if let Some(condition) = &template_task.when {
+                create_edges_from_condition(id, condition, &task_id_by_name)
+                    .with_context(|| format!("error in 'when' of task '{}'", template_task.name))?;
+                template_task.options.when = Some(condition.clone());
+            }
 
             // // try parse operator
             let operator = &serde_json::from_value::<Operator>(json!(template_task.operator)).ok();
@@ -75,7 +284,12 @@ pub fn read_from_yaml(value: Value) {
             // }
 
             if template_task.lazy_expand {
-                assert!(depends_on.len() == 1);
+                if depends_on.len() != 1 {
+                    bail!(
+                        "lazily expanded task '{}' must depend on exactly one task",
+                        template_task.name
+                    );
+                }
                 _expand_lazy_with_function_name::<Value, Vec<Value>, Value>(
                     &_lazy_task_ref(depends_on[0]),
                     &template_task.options,
@@ -83,24 +297,305 @@ pub fn read_from_yaml(value: Value) {
                     &template_task.operator,
                 );
             } else {
+                let template_args =
+                    create_template_args_by_operator(id, value, operator, &task_id_by_name)
+                        .with_context(|| format!("error in task '{}'", template_task.name))?;
                 _add_task_with_function_name::<Value, Value>(
-                    create_template_args_by_operator(id, value, operator, &task_id_by_name),
+                    template_args,
                     &template_task.options,
                     &template_task.name,
                     &template_task.operator,
                     use_trigger_params,
+                    template_task.is_branch,
                 );
             }
         }
+
+        for (name, group, depends_on) in &task_groups {
+            for dependency in depends_on {
+                if let Some((_, upstream_group, _)) =
+                    task_groups.iter().find(|(n, _, _)| n == dependency)
+                {
+                    let _ = upstream_group >> group;
+                    continue;
+                }
+                let upstream_id = get_upstream_id(dependency, &task_id_by_name)
+                    .with_context(|| format!("upstream task of task group '{name}' missing"))?;
+                let _ = _task_ref(HashSet::from([upstream_id])) >> group;
+            }
+        }
+
+        for (name, task) in executable_task_entries {
+            let id = get_id_by_task_name(name).unwrap();
+            let depends_on: Vec<String> = serde_json::from_value(task["depends_on"].clone())
+                .with_context(|| format!("error parsing depends_on of task '{name}'"))?;
+            for dependency in depends_on {
+                let upstream_id = get_upstream_id(&dependency, &task_id_by_name)
+                    .with_context(|| format!("upstream task of task '{name}' missing"))?;
+                get_edges().write().unwrap().insert((upstream_id, id));
+            }
+        }
     }
+
+    // an include's dependencies are waited on by its first tasks
+    for (task_ids, depends_on) in include_dependencies {
+        let mut edges = get_edges().write().unwrap();
+        let first_ids: Vec<usize> = task_ids
+            .iter()
+            .filter(|down| !task_ids.iter().any(|up| edges.contains(&(*up, **down))))
+            .copied()
+            .collect();
+        for dependency in depends_on {
+            let upstream_id = get_upstream_id(&dependency, &task_id_by_name)
+                .context("upstream task of included pipeline missing")?;
+            for id in &first_ids {
+                edges.insert((upstream_id, *id));
+            }
+        }
+    }
+
+    Ok(task_id_by_name)
 }
 
 #[cfg(test)]
 mod test {
-    use std::path::Path;
+    use std::{env, fs, path::Path, sync::Mutex};
+
+    use thepipelinetool_core::dev::{get_edges, get_tasks};
+
+    use super::read_from_yaml;
+
+    // tasks are added to a global pipeline, so tests reading pipelines must not interleave
+    static READ_LOCK: Mutex<()> = Mutex::new(());
 
     #[test]
     fn test() {
         assert!(Path::new("simple.yaml").with_extension("") == Path::new("simple"));
     }
+
+    #[test]
+    fn test_branch() {
+        let _lock = READ_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let task_id_by_name = read_from_yaml(
+            serde_yaml::from_str(
+                "
+tasks:
+  check:
+    script: echo load
+    is_branch: true
+  load:
+    script: echo loading
+    depends_on: [check]
+",
+            )
+            .unwrap(),
+            Path::new("."),
+        )
+        .unwrap();
+
+        let tasks = get_tasks().read().unwrap();
+        let check = &tasks[task_id_by_name["check"]];
+        let load = &tasks[task_id_by_name["load"]];
+        assert!(check.is_branch);
+        assert!(!load.is_branch);
+        assert!(get_edges().read().unwrap().contains(&(check.id, load.id)));
+    }
+
+    #[test]
+    fn test_sub_pipeline() {
+        let _lock = READ_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let dir = env::temp_dir().join("tpt_test_sub_pipeline");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("extract.yaml"),
+            "
+tasks:
+  fetch:
+    script: echo fetch
+  parse:
+    script: echo parse
+    depends_on: [fetch]
+",
+        )
+        .unwrap();
+
+        let task_id_by_name = read_from_yaml(
+            serde_yaml::from_str(
+                "
+tasks:
+  start:
+    script: echo start
+  extract:
+    pipeline: extract.yaml
+    depends_on: [start]
+  report:
+    script: echo report
+    depends_on: [extract]
+",
+            )
+            .unwrap(),
+            &dir,
+        )
+        .unwrap();
+
+        let id = |name: &str| task_id_by_name[name];
+        let edges = get_edges().read().unwrap();
+        assert!(edges.contains(&(id("start"), id("extract/fetch"))));
+        assert!(edges.contains(&(id("extract/fetch"), id("extract/parse"))));
+        assert!(edges.contains(&(id("extract/parse"), id("report"))));
+        assert!(!edges.contains(&(id("start"), id("extract/parse"))));
+        assert!(!edges.contains(&(id("extract/fetch"), id("report"))));
+    }
+
+    fn write_shared_tasks() -> std::path::PathBuf {
+        let dir = env::temp_dir().join("tpt_test_include");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("shared.yaml"),
+            "
+tasks:
+  notify:
+    script: echo notify
+  cleanup:
+    script: echo cleanup
+    depends_on: [notify]
+",
+        )
+        .unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_include() {
+        let _lock = READ_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let dir = write_shared_tasks();
+
+        let task_id_by_name = read_from_yaml(
+            serde_yaml::from_str(
+                "
+include:
+  - shared.yaml
+imports:
+  - path: shared.yaml
+    prefix: team
+    depends_on: [load]
+tasks:
+  load:
+    script: echo load
+  done:
+    script: echo done
+    depends_on: [load, cleanup, team/notify]
+",
+            )
+            .unwrap(),
+            &dir,
+        )
+        .unwrap();
+
+        let tasks = get_tasks().read().unwrap();
+        for name in [
+            "notify",
+            "cleanup",
+            "team/notify",
+            "team/cleanup",
+            "load",
+            "done",
+        ] {
+            assert_eq!(tasks[task_id_by_name[name]].name, name);
+        }
+        let id = |name: &str| task_id_by_name[name];
+        let edges = get_edges().read().unwrap();
+        assert!(edges.contains(&(id("notify"), id("cleanup"))));
+        assert!(edges.contains(&(id("team/notify"), id("team/cleanup"))));
+        assert!(edges.contains(&(id("cleanup"), id("done"))));
+        assert!(edges.contains(&(id("team/notify"), id("done"))));
+        assert!(edges.contains(&(id("load"), id("team/notify"))));
+        assert!(!edges.contains(&(id("load"), id("team/cleanup"))));
+    }
+
+    #[test]
+    fn test_include_conflict() {
+        let _lock = READ_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let dir = write_shared_tasks();
+
+        let err = read_from_yaml(
+            serde_yaml::from_str(
+                "
+include:
+  - shared.yaml
+tasks:
+  notify:
+    script: echo notify
+",
+            )
+            .unwrap(),
+            &dir,
+        )
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("task 'notify' is defined more than once"));
+    }
+
+    #[test]
+    fn test_depend_on_existing_tasks() {
+        let _lock = READ_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        // stands in for the tasks of an executable
+        let existing_task_id_by_name = read_from_yaml(
+            serde_yaml::from_str(
+                "
+tasks:
+  typed:
+    script: echo typed
+",
+            )
+            .unwrap(),
+            Path::new("."),
+        )
+        .unwrap();
+
+        let task_id_by_name = read_from_yaml(
+            serde_yaml::from_str(
+                "
+tasks:
+  prepare:
+    script: echo prepare
+  typed:
+    depends_on: [prepare]
+  publish:
+    script: echo {{typed}}
+",
+            )
+            .unwrap(),
+            Path::new("."),
+        )
+        .unwrap();
+
+        let tasks = get_tasks().read().unwrap();
+        let typed = existing_task_id_by_name["typed"];
+        assert_eq!(tasks.iter().filter(|t| t.name == "typed").count(), 1);
+        let edges = get_edges().read().unwrap();
+        assert!(edges.contains(&(task_id_by_name["prepare"], typed)));
+        assert!(edges.contains(&(typed, task_id_by_name["publish"])));
+        drop(edges);
+        drop(tasks);
+
+        let err = read_from_yaml(
+            serde_yaml::from_str(
+                "
+tasks:
+  orphan:
+    script: echo orphan
+    depends_on: [missing]
+",
+            )
+            .unwrap(),
+            Path::new("."),
+        )
+        .unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "upstream task of task 'orphan' missing: no task named 'missing'"
+        );
+    }
 }
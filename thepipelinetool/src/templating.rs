@@ -1,16 +1,15 @@
 use std::collections::HashMap;
 
+use anyhow::{anyhow, Context, Result};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use thepipelinetool_core::dev::{
     bash::TemplateBashTaskArgs,
-    bash_operator, get_edges, get_id_by_task_name,
+    bash_operator, get_edges, get_id_by_task_name, get_placeholder_expression,
     python::{TemplatePythonArgs, REQUIREMENTS_KEY},
-    Operator, TaskOptions, ORIGINAL_STRING_KEY,
-};
-use thepipelinetool_utils::{
-    function_name_as_string, UPSTREAM_TASK_ID_KEY, UPSTREAM_TASK_RESULT_KEY,
+    Expression, Operator, TaskOptions, ORIGINAL_STRING_KEY, RUN_CONTEXT_NAMES,
 };
+use thepipelinetool_utils::{function_name_as_string, UPSTREAM_TASK_ID_KEY};
 
 fn default_operator() -> String {
     function_name_as_string(bash_operator).to_string()
@@ -35,33 +34,49 @@ pub struct TemplateTask {
 
     #[serde(default)]
     pub depends_on: Vec<String>,
+
+    #[serde(default)]
+    pub when: Option<String>,
 }
 
 const LEFT_INTERPOLATION_IDENTIFIER: &str = "{{";
 const RIGHT_INTERPOLATION_IDENTIFIER: &str = "}}";
 
+/// Finds an upstream task by name among the tasks of this pipeline file, then among all tasks
+/// added so far, like those of a Rust executable.
+pub fn get_upstream_id(name: &str, task_id_by_name: &HashMap<String, usize>) -> Result<usize> {
+    task_id_by_name
+        .get(name)
+        .copied()
+        .or_else(|| get_id_by_task_name(name))
+        .ok_or_else(|| anyhow!("no task named '{name}'"))
+}
+
 pub fn create_template_args_by_operator(
     id: usize,
     value: &Value,
     operator: &Option<Operator>,
     task_id_by_name: &HashMap<String, usize>,
-) -> Value {
+) -> Result<Value> {
     let default_args = json!({});
     // generate template args dependant on operator type
-    match operator {
+    Ok(match operator {
         Some(Operator::BashOperator) => create_template_args_from_string(
             id,
             &serde_json::from_value::<TemplateBashTaskArgs>(value.clone())
-                .expect("error parsing template bash args")
+                .context("error parsing template bash args")?
                 .script,
             task_id_by_name,
-        ),
+        )?,
         Some(Operator::PythonOperator) => {
             let template_python_args = serde_json::from_value::<TemplatePythonArgs>(value.clone())
-                .expect("error parsing template python args");
+                .context("error parsing template python args")?;
 
-            let mut val =
-                create_template_args_from_string(id, &template_python_args.script, task_id_by_name);
+            let mut val = create_template_args_from_string(
+                id,
+                &template_python_args.script,
+                task_id_by_name,
+            )?;
             val[REQUIREMENTS_KEY] = template_python_args.requirements.into();
             val
         }
@@ -74,51 +89,74 @@ pub fn create_template_args_by_operator(
             .get("args")
             .unwrap_or(&default_args)
             .clone(),
+    })
+}
+
+/// The `{{ ... }}` placeholders in a string, with their parsed expressions.
+fn get_placeholders(string: &str) -> Result<Vec<(String, Expression)>> {
+    let mut placeholders = vec![];
+    let mut rest = string;
+
+    while let Some(left) = rest.find(LEFT_INTERPOLATION_IDENTIFIER) {
+        let Some(right) = rest[left..].find(RIGHT_INTERPOLATION_IDENTIFIER) else {
+            break;
+        };
+        let placeholder = &rest[left..(left + right + 2)];
+        let expression = Expression::parse(get_placeholder_expression(placeholder).unwrap())?;
+        placeholders.push((placeholder.to_string(), expression));
+        rest = &rest[(left + right + 2)..];
     }
+    Ok(placeholders)
 }
 
+/// The upstream task an expression reads from, if it doesn't read from the run context.
+fn get_expression_upstream_id(
+    expression: &Expression,
+    task_id_by_name: &HashMap<String, usize>,
+) -> Result<Option<usize>> {
+    if RUN_CONTEXT_NAMES.contains(&expression.root.as_str()) {
+        return Ok(None);
+    }
+    get_upstream_id(&expression.root, task_id_by_name).map(Some)
+}
+
+/// Maps each placeholder of the string to the whole result of the upstream task it reads from,
+/// or to `null` for the run context; expressions are evaluated on those when the task runs.
 pub fn create_template_args_from_string(
     task_id: usize,
     original_string: &str,
     task_id_by_name: &HashMap<String, usize>,
-) -> Value {
+) -> Result<Value> {
     assert!(!original_string.trim().is_empty());
 
     let mut temp_args = json!({ ORIGINAL_STRING_KEY: original_string });
-    let mut temp_string = original_string.to_string();
-
-    loop {
-        let (left, right) = (
-            temp_string.find(LEFT_INTERPOLATION_IDENTIFIER),
-            temp_string.find(RIGHT_INTERPOLATION_IDENTIFIER),
-        );
-
-        if left.is_none() || right.is_none() {
-            break;
-        }
-        let (left, right) = (left.unwrap(), right.unwrap());
-        let chunks: Vec<&str> = temp_string[(left + 2)..(right)].trim().split('.').collect();
 
-        let upstream_task_name = chunks[0];
-        let upstream_id = task_id_by_name
-            .get(upstream_task_name)
-            .copied()
-            .unwrap_or_else(|| get_id_by_task_name(upstream_task_name));
-
-        let to_replace = &temp_string[left..(right + 2)].to_string();
+    for (placeholder, expression) in get_placeholders(original_string)? {
+        temp_args[&placeholder] = match get_expression_upstream_id(&expression, task_id_by_name)? {
+            Some(upstream_id) => {
+                get_edges().write().unwrap().insert((upstream_id, task_id));
+                json!({ UPSTREAM_TASK_ID_KEY: upstream_id })
+            }
+            None => Value::Null,
+        };
+    }
 
-        temp_args[to_replace] = json!({
-            UPSTREAM_TASK_ID_KEY: upstream_id
-        });
+    Ok(temp_args)
+}
 
-        if chunks.len() > 1 {
-            temp_args[to_replace][UPSTREAM_TASK_RESULT_KEY] = chunks[1].into();
+/// Makes the tasks referenced in a `when` condition upstream of the task, so their results are
+/// available when the condition is evaluated.
+pub fn create_edges_from_condition(
+    task_id: usize,
+    condition: &str,
+    task_id_by_name: &HashMap<String, usize>,
+) -> Result<()> {
+    for (_, expression) in get_placeholders(condition)? {
+        if let Some(upstream_id) = get_expression_upstream_id(&expression, task_id_by_name)? {
+            get_edges().write().unwrap().insert((upstream_id, task_id));
         }
-        get_edges().write().unwrap().insert((upstream_id, task_id));
-        temp_string.replace_range(left..(right + 2), "");
     }
-
-    temp_args
+    Ok(())
 }
 
 #[cfg(test)]
@@ -126,7 +164,8 @@ mod tests {
     use std::collections::HashMap;
 
     use serde_json::json;
-    use thepipelinetool_utils::{UPSTREAM_TASK_ID_KEY, UPSTREAM_TASK_RESULT_KEY};
+    use thepipelinetool_core::dev::ORIGINAL_STRING_KEY;
+    use thepipelinetool_utils::UPSTREAM_TASK_ID_KEY;
 
     use crate::templating::create_template_args_from_string;
 
@@ -140,28 +179,49 @@ mod tests {
 
         assert_eq!(
             json!({
+                ORIGINAL_STRING_KEY: "echo {{  t1 }}",
                 "{{  t1 }}": { UPSTREAM_TASK_ID_KEY: 0 }
             }),
-            create_template_args_from_string(1, "echo {{  t1 }}", &task_id_by_name)
+            create_template_args_from_string(1, "echo {{  t1 }}", &task_id_by_name).unwrap()
         );
         assert_eq!(
             json!({
+                ORIGINAL_STRING_KEY: "echo {{  t1 }}{{t2}}",
                 "{{  t1 }}": { UPSTREAM_TASK_ID_KEY: 0 },
                 "{{t2}}": { UPSTREAM_TASK_ID_KEY: 1 }
             }),
-            create_template_args_from_string(1, "echo {{  t1 }}{{t2}}", &task_id_by_name)
+            create_template_args_from_string(1, "echo {{  t1 }}{{t2}}", &task_id_by_name).unwrap()
         );
         assert_eq!(
             json!({
+                ORIGINAL_STRING_KEY: "echo {{  t1 }}{{t2}}{{t3.data[0] | default: 1}}",
                 "{{  t1 }}": { UPSTREAM_TASK_ID_KEY: 0 },
                 "{{t2}}": { UPSTREAM_TASK_ID_KEY: 1 },
-                "{{t3.data}}": { UPSTREAM_TASK_ID_KEY: 2, UPSTREAM_TASK_RESULT_KEY: "data" }
+                "{{t3.data[0] | default: 1}}": { UPSTREAM_TASK_ID_KEY: 2 }
             }),
             create_template_args_from_string(
                 1,
-                "echo {{  t1 }}{{t2}}{{t3.data}}",
+                "echo {{  t1 }}{{t2}}{{t3.data[0] | default: 1}}",
                 &task_id_by_name
             )
+            .unwrap()
+        );
+        assert_eq!(
+            json!({
+                ORIGINAL_STRING_KEY: "echo {{ run_id }} {{ params.name | upper }}",
+                "{{ run_id }}": null,
+                "{{ params.name | upper }}": null
+            }),
+            create_template_args_from_string(
+                1,
+                "echo {{ run_id }} {{ params.name | upper }}",
+                &task_id_by_name
+            )
+            .unwrap()
+        );
+        assert!(create_template_args_from_string(1, "echo {{t4}}", &task_id_by_name).is_err());
+        assert!(
+            create_template_args_from_string(1, "echo {{t1 | nope}}", &task_id_by_name).is_err()
         );
     }
 }
@@ -5,16 +5,48 @@ use crate::{dev::*, statics::*};
 pub fn seq<T: Serialize, G: Serialize>(a: &TaskRef<T>, b: &TaskRef<G>) -> TaskRef<G> {
     let mut last: usize = 0;
     let mut edges = get_edges().write().unwrap();
+    let group_refs = get_task_group_refs().read().unwrap();
 
-    for up in a.0.task_ids.iter() {
-        for down in b.0.task_ids.iter() {
+    // a task group only leads to `b` from its last tasks and only follows `a` from its first
+    // tasks, other refs connect every task
+    let is_group = |task_ids: &HashSet<usize>| group_refs.contains(task_ids);
+    let ups: Vec<usize> = if is_group(&a.0.task_ids) {
+        a.0.task_ids
+            .iter()
+            .filter(|up| {
+                !a.0.task_ids
+                    .iter()
+                    .any(|down| edges.contains(&(**up, *down)))
+            })
+            .copied()
+            .collect()
+    } else {
+        a.0.task_ids.iter().copied().collect()
+    };
+    let downs: Vec<usize> = if is_group(&b.0.task_ids) {
+        b.0.task_ids
+            .iter()
+            .filter(|down| !b.0.task_ids.iter().any(|up| edges.contains(&(*up, **down))))
+            .copied()
+            .collect()
+    } else {
+        b.0.task_ids.iter().copied().collect()
+    };
+
+    for up in ups.iter() {
+        for down in downs.iter() {
             edges.insert((*up, *down));
             last = *down;
         }
     }
 
     TaskRef(TaskRefInner {
-        task_ids: HashSet::from([last]),
+        // a group stays whole so that the tasks after it wait for all of it
+        task_ids: if is_group(&b.0.task_ids) {
+            b.0.task_ids.clone()
+        } else {
+            HashSet::from([last])
+        },
         key: None,
         _marker: std::marker::PhantomData,
     })
@@ -31,3 +63,109 @@ pub fn par<T: Serialize, G: Serialize>(a: &TaskRef<T>, b: &TaskRef<G>) -> TaskRe
         _marker: std::marker::PhantomData,
     })
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+    use std::sync::Mutex;
+
+    use thepipelinetool_task::branch::ChosenArm;
+
+    use crate::dev::*;
+
+    // the tests share the global tasks and edges
+    static LOCK: Mutex<()> = Mutex::new(());
+
+    fn add_task(name: &str) -> TaskRef<Value> {
+        _add_task_with_function_name::<Value, Value>(
+            Value::Null,
+            &TaskOptions::default(),
+            name,
+            name,
+            false,
+            false,
+        )
+    }
+
+    fn id<T: Serialize>(task_ref: &TaskRef<T>) -> usize {
+        *task_ref.0.task_ids.iter().next().unwrap()
+    }
+
+    #[test]
+    fn test_seq_connects_every_task() {
+        let _lock = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let a = add_task("a");
+        let x = add_task("x");
+        let y = add_task("y");
+        let z = add_task("z");
+        let (a_id, x_id, y_id, z_id) = (id(&a), id(&x), id(&y), id(&z));
+
+        let fanned_out = &a >> (&x | &y);
+        assert_eq!(fanned_out.0.task_ids.len(), 1);
+        let _ = (&x | &y) >> &z;
+
+        let edges = get_edges().read().unwrap();
+        for edge in [(a_id, x_id), (a_id, y_id), (x_id, z_id), (y_id, z_id)] {
+            assert!(edges.contains(&edge), "missing edge {edge:?}");
+        }
+        assert!(!edges.contains(&(a_id, z_id)));
+    }
+
+    #[test]
+    fn test_seq_task_group() {
+        let _lock = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let start = add_task("start");
+        let mut ids = vec![];
+        let group = task_group("group", || {
+            let first = add_task("first");
+            let second = add_task("second");
+            ids = vec![id(&first), id(&second)];
+            let _ = first >> second;
+        });
+        let end = add_task("end");
+        let (start_id, end_id) = (id(&start), id(&end));
+
+        let after = &start >> &group;
+        assert_eq!(after.0.task_ids, HashSet::from_iter(ids.clone()));
+        let _ = after >> &end;
+
+        let edges = get_edges().read().unwrap();
+        for edge in [(start_id, ids[0]), (ids[0], ids[1]), (ids[1], end_id)] {
+            assert!(edges.contains(&edge), "missing edge {edge:?}");
+        }
+        assert!(!edges.contains(&(start_id, ids[1])));
+        assert!(!edges.contains(&(ids[0], end_id)));
+    }
+
+    #[test]
+    fn test_switch_in_task_group() {
+        fn choose(_: ()) -> Switch<()> {
+            Switch::new("load", ())
+        }
+        fn load(_: ()) {}
+
+        let _lock = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let mut ids = vec![];
+        let _ = task_group("extract", || {
+            let switch = switch(choose, (), &TaskOptions::default());
+            let arm = add_arm(&switch, "load", load, &TaskOptions::default());
+            ids = vec![id(&switch), id(&arm)];
+        });
+
+        // the switch picks its arm by the name it was added with
+        let tasks = get_tasks().read().unwrap();
+        assert_eq!(tasks[ids[1]].name, "extract/load");
+        assert!(ChosenArm::Named("load".into()).names(&tasks[ids[1]].name, &tasks[ids[0]].name));
+    }
+
+    #[test]
+    fn test_task_group_panic() {
+        let _lock = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let result = std::panic::catch_unwind(|| task_group("failing", || panic!("build failed")));
+
+        assert!(result.is_err());
+        assert_eq!(add_task("after").0.task_ids.len(), 1);
+        let tasks = get_tasks().read().unwrap();
+        assert_eq!(tasks.last().unwrap().name, "after");
+    }
+}
@@ -25,10 +25,10 @@ where
                 id,
                 Task {
                     id,
-                    name: function_name.to_string(),
+                    name: _get_name_in_task_group(&function_name),
                     function: function_name.clone(),
                     template_args: serde_json::to_value(&template_args_vec[i]).unwrap(),
-                    options: *options,
+                    options: options.clone(),
                     lazy_expand: false,
                     is_dynamic: false,
                     is_branch: false,
@@ -65,6 +65,7 @@ where
             &function_name,
             &function_name,
             false,
+            false,
         )
 }
 
@@ -82,6 +83,7 @@ where
         &function_name,
         &function_name,
         false,
+        false,
     )
 }
 
@@ -93,7 +95,14 @@ where
 {
     let function_name = register_function(function);
 
-    _add_task_with_function_name::<T, G>(Value::Null, options, &function_name, &function_name, true)
+    _add_task_with_function_name::<T, G>(
+        Value::Null,
+        options,
+        &function_name,
+        &function_name,
+        true,
+        false,
+    )
 }
 
 pub fn branch<F, K, T, L, J, R, M>(
@@ -112,31 +121,16 @@ where
     L: Fn(T) -> J + 'static + Sync + Send,
     R: Fn(T) -> M + 'static + Sync + Send,
 {
-    let id = get_tasks().read().unwrap().len();
     let function_name = register_function(function);
 
-    {
-        get_tasks().write().unwrap().insert(
-            id,
-            Task {
-                id,
-                name: function_name.to_string(),
-                function: function_name.to_string(),
-                template_args: serde_json::to_value(template_args).unwrap(),
-                options: *options,
-                lazy_expand: false,
-                is_dynamic: false,
-                is_branch: true,
-                use_trigger_params: false,
-            },
-        );
-    }
-
-    let task_ref = TaskRef::<T>(TaskRefInner::<T> {
-        task_ids: HashSet::from([id]),
-        key: None,
-        _marker: std::marker::PhantomData,
-    });
+    let task_ref = _add_task_with_function_name::<K, T>(
+        serde_json::to_value(template_args).unwrap(),
+        options,
+        &function_name,
+        &function_name,
+        false,
+        true,
+    );
 
     (
         add_task_with_ref(left, &task_ref, options),
@@ -144,6 +138,97 @@ where
     )
 }
 
+/// Adds a task that picks which of its downstream arms runs, by returning the arm's name in a
+/// [`Switch`]. Attach arms with [`add_arm`]; the arms that aren't chosen, and everything
+/// downstream of them, are skipped.
+pub fn switch<F, K, T>(function: F, template_args: K, options: &TaskOptions) -> TaskRef<T>
+where
+    K: Serialize + DeserializeOwned + 'static,
+    T: Serialize + 'static,
+    F: Fn(K) -> Switch<T> + 'static + Sync + Send,
+{
+    let function_name = register_function(function);
+
+    _add_task_with_function_name::<K, T>(
+        serde_json::to_value(template_args).unwrap(),
+        options,
+        &function_name,
+        &function_name,
+        false,
+        true,
+    )
+}
+
+/// Adds a task named `name` that runs with the value of `switch` when the switch chooses `name`.
+pub fn add_arm<F, T, G>(
+    switch: &TaskRef<T>,
+    name: &str,
+    function: F,
+    options: &TaskOptions,
+) -> TaskRef<G>
+where
+    T: Serialize + DeserializeOwned + 'static,
+    G: Serialize + 'static,
+    F: Fn(T) -> G + 'static + Sync + Send,
+{
+    let function_name = register_function(function);
+
+    switch
+        >> _add_task_with_function_name::<T, G>(
+            serde_json::to_value(switch).unwrap(),
+            options,
+            name,
+            &function_name,
+            false,
+            false,
+        )
+}
+
+// leaves the innermost task group, even when building it panics
+struct TaskGroupGuard;
+
+impl Drop for TaskGroupGuard {
+    fn drop(&mut self) {
+        get_task_groups()
+            .write()
+            .unwrap_or_else(|err| err.into_inner())
+            .pop();
+    }
+}
+
+/// Builds the tasks added by `build` as a group, prefixing their names with `name`.
+///
+/// The returned `TaskRef` stands for the whole group: on the right of `>>` it waits for the
+/// upstream tasks before any task in the group starts, and on the left the downstream tasks wait
+/// for every task in the group.
+pub fn task_group<F: FnOnce()>(name: &str, build: F) -> TaskRef<Value> {
+    let first_id = get_tasks().read().unwrap().len();
+
+    get_task_groups().write().unwrap().push(name.to_string());
+    {
+        let _guard = TaskGroupGuard;
+        build();
+    }
+
+    let task_ids = HashSet::from_iter(first_id..get_tasks().read().unwrap().len());
+    get_task_group_refs()
+        .write()
+        .unwrap()
+        .push(task_ids.clone());
+
+    _task_ref(task_ids)
+}
+
+/// The [`RunContext`] of the task being run, read from the environment variables set by the
+/// runner.
+///
+/// # Panics
+///
+/// Panics when called outside of a task run by a runner.
+pub fn current_context() -> RunContext {
+    RunContext::from_env().expect("current_context() is only available in a running task")
+}
+
 pub fn expand_lazy<K, F, T, G>(
     function: F,
     task_ref: &TaskRef<T>,
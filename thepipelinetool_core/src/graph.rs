@@ -10,9 +10,46 @@ pub fn get_mermaid_graph(
     let mut out = "".to_string();
     out += "flowchart TD\n";
 
-    for (task_id, (task_name, task_status)) in task_statuses.iter().enumerate() {
+    // tasks in a task group are drawn inside a subgraph for each group in their name
+    let mut task_ids: Vec<usize> = (0..task_statuses.len()).collect();
+    task_ids.sort_by_key(|task_id| groups_in_name(&task_statuses[*task_id].0));
+
+    let mut open_groups: Vec<&str> = vec![];
+    let mut subgraph_count = 0;
+    for task_id in task_ids {
+        let task_name = &task_statuses[task_id].0;
+        let task_groups = groups_in_name(task_name);
+
+        let common = open_groups
+            .iter()
+            .zip(&task_groups)
+            .take_while(|(open, group)| open == group)
+            .count();
+        for _ in common..open_groups.len() {
+            open_groups.pop();
+            out += &format!("{}end\n", "  ".repeat(open_groups.len() + 1));
+        }
+        for group in &task_groups[common..] {
+            out += &format!(
+                "{}subgraph g{subgraph_count} [{group}]\n",
+                "  ".repeat(open_groups.len() + 1)
+            );
+            open_groups.push(group);
+            subgraph_count += 1;
+        }
+
+        let label = task_name.rsplit('/').next().unwrap();
+        out += &format!(
+            "{}id{task_id}({label}_{task_id})\n",
+            "  ".repeat(open_groups.len() + 1)
+        );
+    }
+    for depth in (0..open_groups.len()).rev() {
+        out += &format!("{}end\n", "  ".repeat(depth + 1));
+    }
+
+    for (task_id, (_, task_status)) in task_statuses.iter().enumerate() {
         let styling = get_styling_for_status(task_status);
-        out += &format!("  id{task_id}({task_name}_{task_id})\n");
         out += &format!("  style id{task_id} {styling}\n");
 
         for edge_id in &upstream_ids[&task_id] {
@@ -23,6 +60,13 @@ pub fn get_mermaid_graph(
     out
 }
 
+/// The task groups a task was added in, from its `group/name` style name.
+fn groups_in_name(task_name: &str) -> Vec<&str> {
+    let mut task_groups: Vec<&str> = task_name.split('/').collect();
+    task_groups.pop();
+    task_groups
+}
+
 pub fn get_graphite_graph(
     task_statuses: &[(usize, String, TaskStatus)],
     downstream_ids: &HashMap<usize, Vec<usize>>,
@@ -39,6 +83,7 @@ pub fn get_graphite_graph(
                 "id": task_id.to_string(),
                 "name": name,
                 "next": next,
+                "group": groups_in_name(task_name).join("/"),
                 "status": serde_json::to_string(task_status).unwrap(),
             })
         })
@@ -53,6 +98,7 @@ fn get_styling_for_status(task_status: &TaskStatus) -> String {
         TaskStatus::Running => "color:black,stroke:#90EE90,fill:white,stroke-width:4px".into(),
         TaskStatus::RetryPending => "color:black,stroke:orange,fill:white,stroke-width:4px".into(),
         TaskStatus::Skipped => "color:black,stroke:pink,fill:white,stroke-width:4px".into(),
+        TaskStatus::Cancelled => "color:black,stroke:purple,fill:white,stroke-width:4px".into(),
     }
 }
 
@@ -90,3 +136,44 @@ pub fn get_default_mermaid_graph(tasks: &[Task], edges: &HashSet<(usize, usize)>
 
     get_mermaid_graph(&task_statuses, &upstream_ids)
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use thepipelinetool_task::task_status::TaskStatus;
+
+    use super::get_mermaid_graph;
+
+    #[test]
+    fn test_mermaid_task_groups() {
+        let task_statuses: Vec<(String, TaskStatus)> = [
+            "extract/fetch",
+            "report",
+            "extract/parse/csv",
+            "extract/save",
+        ]
+        .iter()
+        .map(|name| (name.to_string(), TaskStatus::Pending))
+        .collect();
+        let upstream_ids = HashMap::from([(0, vec![]), (1, vec![3]), (2, vec![0]), (3, vec![2])]);
+
+        let graph = get_mermaid_graph(&task_statuses, &upstream_ids);
+        let nodes: Vec<&str> = graph.lines().take_while(|l| !l.contains("style")).collect();
+        assert_eq!(
+            nodes,
+            [
+                "flowchart TD",
+                "  id1(report_1)",
+                "  subgraph g0 [extract]",
+                "    id0(fetch_0)",
+                "    id3(save_3)",
+                "    subgraph g1 [parse]",
+                "      id2(csv_2)",
+                "    end",
+                "  end",
+            ]
+        );
+        assert!(graph.contains("  id3-->id1\n"));
+    }
+}
@@ -10,6 +10,7 @@ pub fn _add_task_with_function_name<T, G>(
     name: &str,
     function_name: &str,
     use_trigger_params: bool,
+    is_branch: bool,
 ) -> TaskRef<G>
 where
     T: Serialize + DeserializeOwned + 'static,
@@ -22,13 +23,13 @@ where
             id,
             Task {
                 id,
-                name: name.to_string(),
+                name: _get_name_in_task_group(name),
                 function: function_name.to_string(),
                 template_args: serde_json::to_value(template_args).unwrap(),
-                options: *options,
+                options: options.clone(),
                 lazy_expand: false,
                 is_dynamic: false,
-                is_branch: false,
+                is_branch,
                 use_trigger_params,
             },
         );
@@ -71,10 +72,10 @@ where
             id,
             Task {
                 id,
-                name: name.to_string(),
+                name: _get_name_in_task_group(name),
                 function: function_name.to_string(),
                 template_args: serde_json::to_value(task_ref).unwrap(),
-                options: *options,
+                options: options.clone(),
                 lazy_expand: true,
                 is_dynamic: false,
                 is_branch: false,
@@ -91,6 +92,15 @@ where
         })
 }
 
+/// Prefixes `name` with the task groups currently being built, like `extract/load`.
+pub fn _get_name_in_task_group(name: &str) -> String {
+    let task_groups = get_task_groups().read().unwrap();
+    if task_groups.is_empty() {
+        return name.to_string();
+    }
+    format!("{}/{name}", task_groups.join("/"))
+}
+
 pub fn _wrap_function<K, T, F>(function: F) -> impl Fn(Value) -> Value
 where
     T: Serialize,
@@ -27,9 +27,12 @@ pub mod prelude {
 
     pub use serde::{Deserialize, Serialize};
     pub use serde_json::{json, Value};
-    pub use thepipelinetool_task::branch::Branch;
+    pub use thepipelinetool_task::branch::{Branch, Switch};
+    pub use thepipelinetool_task::retry_policy::RetryPolicy;
+    pub use thepipelinetool_task::run_context::RunContext;
     pub use thepipelinetool_task::task_options::TaskOptions;
     pub use thepipelinetool_task::trigger_rule::TriggerRule;
+    pub use thepipelinetool_task::weight_rule::WeightRule;
 }
 
 pub mod dev {
@@ -37,6 +40,15 @@ pub mod dev {
 
     pub use thepipelinetool_task::task_ref_inner::TaskRefInner;
 
+    pub fn _task_ref(task_ids: HashSet<usize>) -> TaskRef<Value> {
+        TaskRef(TaskRefInner {
+            task_ids,
+            key: None,
+
+            _marker: std::marker::PhantomData,
+        })
+    }
+
     pub fn _lazy_task_ref(id: usize) -> TaskRef<Vec<Value>> {
         TaskRef(TaskRefInner {
             task_ids: HashSet::from([id]),
@@ -52,11 +64,16 @@ pub mod dev {
     pub use crate::helpers::*;
     pub use crate::prelude::*;
     pub use crate::statics::*;
-    pub use thepipelinetool_task::ordered_queued_task::OrderedQueuedTask;
+    pub use thepipelinetool_task::ordered_queued_task::{get_queue_score, OrderedQueuedTask};
     pub use thepipelinetool_task::queued_task::QueuedTask;
+    pub use thepipelinetool_task::resource_pool::ResourcePool;
+    pub use thepipelinetool_task::task_options::DEFAULT_QUEUE;
     pub use thepipelinetool_task::task_result::TaskResult;
     pub use thepipelinetool_task::task_status::TaskStatus;
     pub use thepipelinetool_task::temp_queued_task::TempQueuedTask;
+    pub use thepipelinetool_task::template::{
+        get_placeholder_expression, Expression, RUN_CONTEXT_NAMES,
+    };
     pub use thepipelinetool_task::Task;
     pub use thepipelinetool_utils::*;
 }
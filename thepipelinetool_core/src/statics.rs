@@ -5,10 +5,14 @@ use std::sync::{OnceLock, RwLock};
 type StaticTasks = RwLock<Vec<Task>>;
 type StaticFunctions = RwLock<HashMap<String, Box<dyn Fn(Value) -> Value + Sync + Send>>>;
 type StaticEdges = RwLock<HashSet<(usize, usize)>>;
+type StaticTaskGroups = RwLock<Vec<String>>;
+type StaticTaskGroupRefs = RwLock<Vec<HashSet<usize>>>;
 
 static TASKS: OnceLock<StaticTasks> = OnceLock::new();
 static FUNCTIONS: OnceLock<StaticFunctions> = OnceLock::new();
 static EDGES: OnceLock<StaticEdges> = OnceLock::new();
+static TASK_GROUPS: OnceLock<StaticTaskGroups> = OnceLock::new();
+static TASK_GROUP_REFS: OnceLock<StaticTaskGroupRefs> = OnceLock::new();
 
 pub fn get_tasks() -> &'static StaticTasks {
     TASKS.get_or_init(StaticTasks::default)
@@ -22,16 +26,25 @@ pub fn get_edges() -> &'static StaticEdges {
     EDGES.get_or_init(StaticEdges::default)
 }
 
+/// Names of the task groups currently being built, outermost first.
+pub fn get_task_groups() -> &'static StaticTaskGroups {
+    TASK_GROUPS.get_or_init(StaticTaskGroups::default)
+}
+
+/// Task ids of every group built with `task_group`.
+pub fn get_task_group_refs() -> &'static StaticTaskGroupRefs {
+    TASK_GROUP_REFS.get_or_init(StaticTaskGroupRefs::default)
+}
+
 pub fn function_with_name_exists(task_name: &str) -> bool {
     get_functions().read().unwrap().contains_key(task_name)
 }
 
-pub fn get_id_by_task_name(name: &str) -> usize {
+pub fn get_id_by_task_name(name: &str) -> Option<usize> {
     get_tasks()
         .read()
         .unwrap()
         .iter()
         .find(|t| t.name == name)
-        .unwrap_or_else(|| panic!("missing task {name}"))
-        .id
+        .map(|t| t.id)
 }
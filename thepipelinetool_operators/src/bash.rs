@@ -10,15 +10,59 @@ pub struct TemplateBashTaskArgs {
     pub script: String,
 }
 
-pub fn bash_operator(args: Value) -> Value {
-    if args.is_object() {
-        let mut command_string = args[ORIGINAL_STRING_KEY].as_str().unwrap().to_string();
+/// Filter that splices a template value into the script unquoted, like `{{ flags | raw }}`.
+pub const RAW_FILTER: &str = "raw";
+
+// strings are used without their JSON quotes
+fn to_text(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Quotes a value as a single shell word.
+pub fn shell_quote(value: &Value) -> String {
+    format!("'{}'", to_text(value).replace('\'', r"'\''"))
+}
+
+fn is_raw(placeholder: &str) -> bool {
+    placeholder
+        .trim_start_matches('{')
+        .trim_end_matches('}')
+        .split('|')
+        .skip(1)
+        .any(|filter| filter.trim() == RAW_FILTER)
+}
 
-        for (k, v) in args.as_object().unwrap() {
-            if k != ORIGINAL_STRING_KEY {
-                command_string = command_string.replace(k, &v.to_string());
-            }
+/// Replaces each template placeholder in the script with its value, shell-quoted unless the
+/// placeholder opts in to raw interpolation. The script is rendered in one pass, so values are
+/// never scanned for placeholders themselves.
+pub fn render_command(args: &Value) -> String {
+    let mut rest = args[ORIGINAL_STRING_KEY].as_str().unwrap();
+    let mut command_string = String::new();
+
+    while let Some(left) = rest.find("{{") {
+        let Some(right) = rest[left..].find("}}") else {
+            break;
+        };
+        let end = left + right + 2;
+        let placeholder = &rest[left..end];
+        command_string.push_str(&rest[..left]);
+        match args.get(placeholder) {
+            Some(value) if is_raw(placeholder) => command_string.push_str(&to_text(value)),
+            Some(value) => command_string.push_str(&shell_quote(value)),
+            None => command_string.push_str(placeholder),
         }
+        rest = &rest[end..];
+    }
+    command_string.push_str(rest);
+    command_string
+}
+
+pub fn bash_operator(args: Value) -> Value {
+    if args.is_object() {
+        let command_string = render_command(&args);
 
         println!("bash_operator$ {}", command_string);
         run_bash_command(&["bash", "-c", &command_string], true, true)
@@ -34,3 +78,48 @@ pub fn bash_operator(args: Value) -> Value {
         run_bash_command(&args, true, true)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::process::Command;
+
+    use serde_json::json;
+
+    use crate::ORIGINAL_STRING_KEY;
+
+    use super::render_command;
+
+    #[test]
+    fn test_render_command() {
+        let value = "it's $(echo injected) `id`; echo \"done\"\nnext";
+        let args = json!({
+            ORIGINAL_STRING_KEY: "printf %s {{name}}",
+            "{{name}}": value,
+        });
+        let command = render_command(&args);
+        let output = Command::new("bash")
+            .args(["-c", &command])
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), value);
+
+        let args = json!({
+            ORIGINAL_STRING_KEY: "echo {{count}} {{files | raw}} {{ row.name }}",
+            "{{count}}": 3,
+            "{{files | raw}}": "a.csv",
+            "{{ row.name }}": {"a": 1},
+        });
+        assert_eq!(render_command(&args), r#"echo '3' a.csv '{"a":1}'"#);
+
+        // values that look like placeholders are not substituted again
+        let args = json!({
+            ORIGINAL_STRING_KEY: "printf %s {{a}} {{b}}",
+            "{{a}}": "{{b}}'",
+            "{{b}}": "; touch /tmp/pwned; #",
+        });
+        assert_eq!(
+            render_command(&args),
+            r#"printf %s '{{b}}'\''' '; touch /tmp/pwned; #'"#
+        );
+    }
+}
@@ -1,14 +1,14 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde_json::Value;
 use thepipelinetool_task::{
-    task_options::TaskOptions, task_result::TaskResult, task_status::TaskStatus,
-    temp_queued_task::TempQueuedTask, Task,
+    resource_pool::ResourcePool, task_options::TaskOptions, task_result::TaskResult,
+    task_status::TaskStatus, temp_queued_task::TempQueuedTask, Task,
 };
 
-use crate::run::Run;
+use crate::run::{DataInterval, Run};
 
 pub type UpstreamId = usize;
 pub type DownstreamId = usize;
@@ -24,6 +24,7 @@ pub trait Backend {
 
     fn print_priority_queue(&mut self) -> Result<()>;
     fn pop_priority_queue(&mut self) -> Result<Option<TempQueuedTask>>;
+    fn remove_run_from_priority_queue(&mut self, run_id: usize) -> Result<()>;
     fn enqueue_task(
         &mut self,
         run_id: usize,
@@ -31,6 +32,7 @@ pub trait Backend {
         scheduled_date_for_run: DateTime<Utc>,
         pipeline_name: String,
         is_dynamic: bool,
+        not_before: Option<DateTime<Utc>>,
     ) -> Result<()>;
 
     fn get_log(&mut self, run_id: usize, task_id: usize, attempt: usize) -> Result<String>;
@@ -51,6 +53,14 @@ pub trait Backend {
     fn insert_task_results(&mut self, run_id: usize, result: &TaskResult) -> Result<()>;
 
     fn get_task_status(&self, run_id: usize, task_id: usize) -> Result<TaskStatus>;
+    fn get_task_status_closure(
+        &self,
+        run_id: usize,
+        task_id: usize,
+    ) -> Result<Box<dyn Fn() -> Result<TaskStatus> + Send>>;
+    /// Whether runs can be cancelled from outside the process running their tasks. Only then do
+    /// tasks watch their status while they run.
+    fn supports_cancellation(&self) -> bool;
     fn set_task_status(
         &mut self,
         run_id: usize,
@@ -89,6 +99,15 @@ pub trait Backend {
     ) -> Result<()>;
     fn set_task_depth(&mut self, run_id: usize, task_id: usize, depth: usize) -> Result<()>;
     fn delete_task_depth(&mut self, run_id: usize, task_id: usize) -> Result<()>;
+    /// Priority weights are computed once per task, see `BlanketBackend::get_priority_weight`.
+    fn get_cached_priority_weight(&self, run_id: usize, task_id: usize) -> Result<Option<usize>>;
+    fn set_cached_priority_weight(
+        &mut self,
+        run_id: usize,
+        task_id: usize,
+        priority_weight: usize,
+    ) -> Result<()>;
+    fn delete_cached_priority_weight(&mut self, run_id: usize, task_id: usize) -> Result<()>;
 
     fn get_attempt_by_task_id(
         &self,
@@ -96,8 +115,38 @@ pub trait Backend {
         task_id: usize,
         is_dynamic: bool,
     ) -> Result<usize>;
+    /// Remembers the attempts made so far, which don't count towards max_attempts after the
+    /// task is cleared.
+    fn set_cleared_attempts(&mut self, run_id: usize, task_id: usize) -> Result<()>;
+    fn get_cleared_attempts(&self, run_id: usize, task_id: usize) -> Result<usize>;
 
-    fn create_new_run(&mut self, scheduled_date_for_run: DateTime<Utc>) -> Result<Run>;
+    fn create_new_run(
+        &mut self,
+        scheduled_date_for_run: DateTime<Utc>,
+        data_interval: Option<DataInterval>,
+    ) -> Result<Run>;
+    /// Creates a run unless the pipeline already has `max_active_runs` active runs. The check
+    /// and the creation are one atomic step, so concurrent triggers can't both pass the check.
+    fn create_new_run_below(
+        &mut self,
+        scheduled_date_for_run: DateTime<Utc>,
+        data_interval: Option<DataInterval>,
+        max_active_runs: usize,
+    ) -> Result<Option<Run>>;
+    fn set_data_interval(&mut self, run_id: usize, data_interval: &DataInterval) -> Result<()>;
+    fn get_data_interval(&mut self, run_id: usize) -> Result<Option<DataInterval>>;
+    fn set_trigger_params(&mut self, run_id: usize, trigger_params: &Value) -> Result<()>;
+    fn get_trigger_params(&mut self, run_id: usize) -> Result<Value>;
+
+    /// Runs of this pipeline that haven't been seen finishing yet.
+    fn get_active_run_ids(&mut self) -> Result<Vec<usize>>;
+    fn add_active_run_id(&mut self, run_id: usize) -> Result<()>;
+    fn remove_active_run_id(&mut self, run_id: usize) -> Result<()>;
+
+    /// Resource pools are shared by every pipeline of the backend.
+    fn get_resource_pools(&mut self) -> Result<BTreeMap<String, ResourcePool>>;
+    fn set_resource_pool(&mut self, name: &str, resource_pool: &ResourcePool) -> Result<()>;
+    fn delete_resource_pool(&mut self, name: &str) -> Result<()>;
 
     fn remove_edge(&mut self, run_id: usize, edge: (usize, usize)) -> Result<()>;
     fn insert_edge(&mut self, run_id: usize, edge: (usize, usize)) -> Result<()>;
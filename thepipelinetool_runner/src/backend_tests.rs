@@ -0,0 +1,1591 @@
+//! Behavioral checks shared by every `Backend` implementation.
+//!
+//! Each backend module instantiates the suite with `backend_test_suite!`, passing a function
+//! that builds an empty backend from a pipeline's default tasks and edges.
+
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use chrono::Utc;
+use serde_json::{json, Value};
+use thepipelinetool_task::{
+    branch::Switch, ordered_queued_task::MAX_PRIORITY_WEIGHT, queued_task::QueuedTask,
+    resource_pool::ResourcePool, retry_policy::RetryPolicy, task_options::TaskOptions,
+    task_result::TaskResult, task_status::TaskStatus, temp_queued_task::TempQueuedTask,
+    trigger_rule::TriggerRule, weight_rule::WeightRule, Task,
+};
+use thepipelinetool_utils::UPSTREAM_TASK_ID_KEY;
+
+use crate::{
+    blanket_backend::BlanketBackend,
+    pipeline_options::PipelineOptions,
+    run::{DataInterval, RunStatus},
+    Backend,
+};
+
+pub type NewBackend<B> = fn(&[Task], &HashSet<(usize, usize)>, &PipelineOptions) -> B;
+
+macro_rules! backend_test_suite {
+    ($new_backend:expr) => {
+        #[test]
+        fn test_enqueue_run_creates_pending_tasks() {
+            crate::backend_tests::enqueue_run_creates_pending_tasks($new_backend);
+        }
+
+        #[test]
+        fn test_edges_and_dependencies() {
+            crate::backend_tests::edges_and_dependencies($new_backend);
+        }
+
+        #[test]
+        fn test_priority_queue() {
+            crate::backend_tests::priority_queue($new_backend);
+        }
+
+        #[test]
+        fn test_priority_weight() {
+            crate::backend_tests::priority_weight($new_backend);
+        }
+
+        #[test]
+        fn test_max_priority_weight() {
+            crate::backend_tests::max_priority_weight($new_backend);
+        }
+
+        #[test]
+        fn test_results_and_logs() {
+            crate::backend_tests::results_and_logs($new_backend);
+        }
+
+        #[test]
+        fn test_template_args() {
+            crate::backend_tests::template_args($new_backend);
+        }
+
+        #[test]
+        fn test_handle_task_result() {
+            crate::backend_tests::handle_task_result($new_backend);
+        }
+
+        #[test]
+        fn test_retry() {
+            crate::backend_tests::retry($new_backend);
+        }
+
+        #[test]
+        fn test_retry_delay() {
+            crate::backend_tests::retry_delay($new_backend);
+        }
+
+        #[test]
+        fn test_pipeline_retry_defaults() {
+            crate::backend_tests::pipeline_retry_defaults($new_backend);
+        }
+
+        #[test]
+        fn test_trigger_rules() {
+            crate::backend_tests::trigger_rules($new_backend);
+        }
+
+        #[test]
+        fn test_branch_join() {
+            crate::backend_tests::branch_join($new_backend);
+        }
+
+        #[test]
+        fn test_switch() {
+            crate::backend_tests::switch($new_backend);
+        }
+
+        #[test]
+        fn test_switch_in_task_group() {
+            crate::backend_tests::switch_in_task_group($new_backend);
+        }
+
+        #[test]
+        fn test_when_condition() {
+            crate::backend_tests::when_condition($new_backend);
+        }
+
+        #[test]
+        fn test_when_skipped_upstream() {
+            crate::backend_tests::when_skipped_upstream($new_backend);
+        }
+
+        #[test]
+        fn test_template_expressions() {
+            crate::backend_tests::template_expressions($new_backend);
+        }
+
+        #[test]
+        fn test_data_interval() {
+            crate::backend_tests::data_interval($new_backend);
+        }
+
+        #[test]
+        fn test_trigger_params() {
+            crate::backend_tests::trigger_params($new_backend);
+        }
+
+        #[test]
+        fn test_active_runs() {
+            crate::backend_tests::active_runs($new_backend);
+        }
+
+        #[test]
+        fn test_max_active_tasks() {
+            crate::backend_tests::max_active_tasks($new_backend);
+        }
+
+        #[test]
+        fn test_max_concurrency() {
+            crate::backend_tests::max_concurrency($new_backend);
+        }
+
+        #[test]
+        fn test_resource_pools() {
+            crate::backend_tests::resource_pools($new_backend);
+        }
+
+        #[test]
+        fn test_exit_codes() {
+            crate::backend_tests::exit_codes($new_backend);
+        }
+
+        #[test]
+        fn test_delayed_tasks() {
+            crate::backend_tests::delayed_tasks($new_backend);
+        }
+
+        #[test]
+        fn test_concurrent_runs() {
+            crate::backend_tests::concurrent_runs($new_backend);
+        }
+
+        #[test]
+        fn test_cancel_run() {
+            crate::backend_tests::cancel_run($new_backend);
+        }
+
+        #[test]
+        fn test_clear_task() {
+            crate::backend_tests::clear_task($new_backend);
+        }
+    };
+}
+
+pub(crate) use backend_test_suite;
+
+fn task(id: usize, name: &str, template_args: Value, options: TaskOptions) -> Task {
+    Task {
+        id,
+        name: name.to_string(),
+        function: "print_operator".to_string(),
+        template_args,
+        options,
+        lazy_expand: false,
+        is_dynamic: false,
+        is_branch: false,
+        use_trigger_params: false,
+    }
+}
+
+/// produce -> consume (through a template arg reference) -> after (through an explicit edge)
+fn default_pipeline(options: TaskOptions) -> (Vec<Task>, HashSet<(usize, usize)>) {
+    (
+        vec![
+            task(0, "produce", json!({}), options.clone()),
+            task(
+                1,
+                "consume",
+                json!({ UPSTREAM_TASK_ID_KEY: 0 }),
+                options.clone(),
+            ),
+            task(2, "after", json!({}), options),
+        ],
+        HashSet::from([(1, 2)]),
+    )
+}
+
+fn start_run<B: Backend + Send + Sync>(new_backend: NewBackend<B>) -> (B, usize) {
+    let (tasks, edges) = default_pipeline(TaskOptions::default());
+    let mut backend = new_backend(&tasks, &edges, &PipelineOptions::default());
+    let run = backend.create_new_run(Utc::now(), None).unwrap();
+    backend.enqueue_run(&run, None).unwrap();
+    (backend, run.run_id)
+}
+
+fn result_for(task: &Task, attempt: usize, success: bool, result: Value) -> TaskResult {
+    TaskResult {
+        task_id: task.id,
+        result,
+        attempt,
+        max_attempts: task.options.max_attempts,
+        name: task.name.clone(),
+        function: task.function.clone(),
+        success,
+        resolved_args_str: "".into(),
+        started: None,
+        ended: None,
+        elapsed: 0,
+        premature_failure: false,
+        premature_failure_error_str: "".into(),
+        is_branch: task.is_branch,
+        is_sensor: task.options.is_sensor,
+        exit_code: Some(if success { 0 } else { 1 }),
+        skipped: false,
+    }
+}
+
+fn pop_and_finish<B: Backend + Send + Sync>(
+    backend: &mut B,
+    success: bool,
+    result: Value,
+) -> TaskResult {
+    let temp_queued_task = backend.pop_priority_queue().unwrap().unwrap();
+    let queued_task = &temp_queued_task.queued_task;
+    let mut task = backend
+        .get_task_by_id(queued_task.run_id, queued_task.task_id)
+        .unwrap();
+    task.options.max_attempts += backend
+        .get_cleared_attempts(queued_task.run_id, queued_task.task_id)
+        .unwrap();
+    let task_result = result_for(&task, queued_task.attempt, success, result);
+
+    backend
+        .handle_task_result(queued_task.run_id, queued_task, task_result.clone())
+        .unwrap();
+    backend.remove_from_temp_queue(&temp_queued_task).unwrap();
+    task_result
+}
+
+pub fn enqueue_run_creates_pending_tasks<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let (mut backend, run_id) = start_run(new_backend);
+
+    let tasks = backend.get_all_tasks(run_id).unwrap();
+    assert_eq!(
+        tasks.iter().map(|t| t.name.as_str()).collect::<Vec<&str>>(),
+        vec!["produce", "consume", "after"]
+    );
+    for task in &tasks {
+        assert_eq!(
+            backend.get_task_status(run_id, task.id).unwrap(),
+            TaskStatus::Pending
+        );
+    }
+    assert_eq!(backend.get_run_status(run_id).unwrap(), RunStatus::Pending);
+
+    backend
+        .set_task_status(run_id, 2, TaskStatus::Skipped)
+        .unwrap();
+    assert_eq!(
+        backend.get_task_status(run_id, 2).unwrap(),
+        TaskStatus::Skipped
+    );
+}
+
+pub fn edges_and_dependencies<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let (mut backend, run_id) = start_run(new_backend);
+
+    assert_eq!(backend.get_downstream(run_id, 0).unwrap(), vec![1]);
+    assert_eq!(backend.get_upstream(run_id, 1).unwrap(), vec![0]);
+    assert_eq!(backend.get_upstream(run_id, 2).unwrap(), vec![1]);
+    assert_eq!(
+        backend.get_dependencies(run_id, 1).unwrap(),
+        HashMap::from([((0, "".to_string()), "".to_string())])
+    );
+
+    assert_eq!(backend.get_task_depth(run_id, 0).unwrap(), 0);
+    assert_eq!(backend.get_task_depth(run_id, 2).unwrap(), 2);
+    backend.set_task_depth(run_id, 2, 5).unwrap();
+    assert_eq!(backend.get_task_depth(run_id, 2).unwrap(), 5);
+    backend.delete_task_depth(run_id, 2).unwrap();
+    assert_eq!(backend.get_task_depth(run_id, 2).unwrap(), 2);
+
+    backend.remove_edge(run_id, (0, 1)).unwrap();
+    assert!(backend.get_downstream(run_id, 0).unwrap().is_empty());
+    assert!(backend.get_dependencies(run_id, 1).unwrap().is_empty());
+
+    backend.insert_edge(run_id, (0, 2)).unwrap();
+    let mut upstream = backend.get_upstream(run_id, 2).unwrap();
+    upstream.sort();
+    assert_eq!(upstream, vec![0, 1]);
+}
+
+pub fn priority_queue<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let (mut backend, run_id) = start_run(new_backend);
+    let pipeline_name = backend.get_pipeline_name().unwrap();
+
+    // only the root task is enqueued by enqueue_run
+    assert_eq!(backend.get_queue_length().unwrap(), 1);
+
+    backend
+        .enqueue_task(run_id, 2, Utc::now(), pipeline_name.clone(), false, None)
+        .unwrap();
+    // enqueueing a task again replaces its previous entry
+    backend
+        .enqueue_task(run_id, 2, Utc::now(), pipeline_name, false, None)
+        .unwrap();
+    assert_eq!(backend.get_queue_length().unwrap(), 2);
+    // the scores of tasks with a priority weight are negative
+    backend.print_priority_queue().unwrap();
+
+    // lower depth is popped first
+    let first = backend.pop_priority_queue().unwrap().unwrap();
+    assert_eq!(first.queued_task.task_id, 0);
+    assert_eq!(first.queued_task.attempt, 1);
+
+    let second = backend.pop_priority_queue().unwrap().unwrap();
+    assert_eq!(second.queued_task.task_id, 2);
+    assert_eq!(second.queued_task.attempt, 2);
+    assert_eq!(second.queued_task.run_id, run_id);
+
+    assert!(backend.pop_priority_queue().unwrap().is_none());
+    assert_eq!(backend.get_queue_length().unwrap(), 0);
+
+    backend.remove_from_temp_queue(&first).unwrap();
+    backend.remove_from_temp_queue(&second).unwrap();
+}
+
+pub fn priority_weight<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let weighted = |priority_weight, weight_rule| TaskOptions {
+        priority_weight,
+        weight_rule,
+        ..Default::default()
+    };
+    let tasks = vec![
+        task(0, "backfill", json!({}), TaskOptions::default()),
+        task(1, "report", json!({}), weighted(1, WeightRule::Downstream)),
+        task(2, "aggregate", json!({}), weighted(2, WeightRule::Absolute)),
+        task(3, "publish", json!({}), weighted(3, WeightRule::Upstream)),
+        task(4, "alert", json!({}), weighted(10, WeightRule::Absolute)),
+    ];
+    let mut backend = new_backend(
+        &tasks,
+        &HashSet::from([(1, 2), (2, 3), (1, 3)]),
+        &PipelineOptions {
+            priority_weight: 5,
+            ..Default::default()
+        },
+    );
+    let run = backend.create_new_run(Utc::now(), None).unwrap();
+    backend.enqueue_run(&run, None).unwrap();
+
+    // related tasks count once, however many paths lead to them
+    assert_eq!(backend.get_priority_weight(run.run_id, 1).unwrap(), 6);
+    assert_eq!(backend.get_priority_weight(run.run_id, 2).unwrap(), 2);
+    assert_eq!(backend.get_priority_weight(run.run_id, 3).unwrap(), 6);
+
+    // weights that follow the graph are only computed once
+    assert_eq!(
+        backend.get_cached_priority_weight(run.run_id, 1).unwrap(),
+        Some(6)
+    );
+    assert_eq!(
+        backend.get_cached_priority_weight(run.run_id, 2).unwrap(),
+        None
+    );
+    backend
+        .set_cached_priority_weight(run.run_id, 3, 7)
+        .unwrap();
+    assert_eq!(backend.get_priority_weight(run.run_id, 3).unwrap(), 7);
+    backend
+        .delete_cached_priority_weight(run.run_id, 3)
+        .unwrap();
+    assert_eq!(backend.get_priority_weight(run.run_id, 3).unwrap(), 6);
+
+    // higher weights are popped first, regardless of the order tasks were enqueued in, and the
+    // pipeline's weight is added to every task
+    let mut popped = vec![];
+    while let Some(temp_queued_task) = backend.pop_priority_queue().unwrap() {
+        backend.remove_from_temp_queue(&temp_queued_task).unwrap();
+        popped.push((
+            temp_queued_task.queued_task.task_id,
+            temp_queued_task.queued_task.priority_weight,
+        ));
+    }
+    assert_eq!(popped, [(4, 15), (1, 11), (0, 6)]);
+}
+
+pub fn max_priority_weight<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let weighted = |priority_weight| TaskOptions {
+        priority_weight,
+        weight_rule: WeightRule::Downstream,
+        ..Default::default()
+    };
+    let tasks = vec![
+        task(0, "huge", json!({}), weighted(usize::MAX)),
+        task(1, "also_huge", json!({}), weighted(usize::MAX)),
+        task(2, "small", json!({}), weighted(1)),
+    ];
+    let mut backend = new_backend(
+        &tasks,
+        &HashSet::from([(0, 1)]),
+        &PipelineOptions {
+            priority_weight: usize::MAX,
+            ..Default::default()
+        },
+    );
+    let run = backend.create_new_run(Utc::now(), None).unwrap();
+    backend.enqueue_run(&run, None).unwrap();
+
+    // huge weights are clamped instead of overflowing, and still come first
+    assert_eq!(
+        backend.get_priority_weight(run.run_id, 0).unwrap(),
+        MAX_PRIORITY_WEIGHT
+    );
+    let first = backend.pop_priority_queue().unwrap().unwrap();
+    assert_eq!(first.queued_task.task_id, 0);
+    assert_eq!(first.queued_task.priority_weight, usize::MAX);
+    assert_eq!(
+        backend
+            .pop_priority_queue()
+            .unwrap()
+            .unwrap()
+            .queued_task
+            .task_id,
+        2
+    );
+}
+
+pub fn results_and_logs<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let (mut backend, run_id) = start_run(new_backend);
+
+    let log = backend.get_log_handle_closure(run_id, 0, 1).unwrap();
+    log("first\n".to_string()).unwrap();
+    log("last\n".to_string()).unwrap();
+    assert_eq!(backend.get_log(run_id, 0, 1).unwrap(), "first\nlast\n");
+
+    let take_last_stdout_line = backend.take_last_stdout_line(run_id, 0, 1).unwrap();
+    assert_eq!(take_last_stdout_line().unwrap(), "last\n");
+    assert_eq!(backend.get_log(run_id, 0, 1).unwrap(), "first\n");
+
+    let task = backend.get_task_by_id(run_id, 0).unwrap();
+    backend
+        .insert_task_results(run_id, &result_for(&task, 1, false, Value::Null))
+        .unwrap();
+    backend
+        .insert_task_results(run_id, &result_for(&task, 2, true, json!({"a": 1})))
+        .unwrap();
+
+    let result = backend.get_task_result(run_id, 0).unwrap();
+    assert!(result.success);
+    assert_eq!(result.attempt, 2);
+    assert_eq!(result.result, json!({"a": 1}));
+}
+
+pub fn template_args<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let (mut backend, run_id) = start_run(new_backend);
+
+    assert_eq!(backend.get_template_args(run_id, 2).unwrap(), json!({}));
+    backend
+        .set_template_args(run_id, 2, &json!({"a": [1, 2]}).to_string())
+        .unwrap();
+    assert_eq!(
+        backend.get_template_args(run_id, 2).unwrap(),
+        json!({"a": [1, 2]})
+    );
+    assert_eq!(
+        backend.get_task_by_id(run_id, 2).unwrap().template_args,
+        json!({"a": [1, 2]})
+    );
+
+    // unknown tasks and runs are errors
+    assert!(backend.get_task_by_id(run_id, 3).is_err());
+    assert!(backend.get_template_args(run_id, 3).is_err());
+    assert!(backend.get_task_by_id(run_id + 1, 0).is_err());
+    assert!(backend.get_template_args(run_id + 1, 0).is_err());
+    assert!(backend.set_template_args(run_id, 3, "{}").is_err());
+    assert!(backend.get_task_result(run_id, 3).is_err());
+}
+
+pub fn handle_task_result<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let (mut backend, run_id) = start_run(new_backend);
+
+    let produce = pop_and_finish(&mut backend, true, json!("hello"));
+    assert_eq!(produce.task_id, 0);
+    assert_eq!(
+        backend.get_task_status(run_id, 0).unwrap(),
+        TaskStatus::Success
+    );
+    assert_eq!(backend.get_run_status(run_id).unwrap(), RunStatus::Running);
+
+    // consume resolves its argument from the upstream result
+    let consume = backend.get_task_by_id(run_id, 1).unwrap();
+    let dependencies = backend.get_dependencies(run_id, 1).unwrap();
+    assert_eq!(
+        backend
+            .resolve_args(run_id, &consume.template_args, &dependencies)
+            .unwrap(),
+        json!("hello")
+    );
+
+    assert_eq!(
+        pop_and_finish(&mut backend, true, json!("hello")).task_id,
+        1
+    );
+    assert_eq!(pop_and_finish(&mut backend, true, Value::Null).task_id, 2);
+    assert!(backend.pop_priority_queue().unwrap().is_none());
+    assert_eq!(backend.get_run_status(run_id).unwrap(), RunStatus::Success);
+}
+
+pub fn retry<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let (tasks, edges) = default_pipeline(TaskOptions {
+        max_attempts: 2,
+        ..Default::default()
+    });
+    let mut backend = new_backend(&tasks, &edges, &PipelineOptions::default());
+    let run = backend.create_new_run(Utc::now(), None).unwrap();
+    backend.enqueue_run(&run, None).unwrap();
+
+    pop_and_finish(&mut backend, false, Value::Null);
+    assert_eq!(
+        backend.get_task_status(run.run_id, 0).unwrap(),
+        TaskStatus::RetryPending
+    );
+
+    let second_attempt = pop_and_finish(&mut backend, false, Value::Null);
+    assert_eq!(second_attempt.task_id, 0);
+    assert_eq!(second_attempt.attempt, 2);
+    assert_eq!(
+        backend.get_task_status(run.run_id, 0).unwrap(),
+        TaskStatus::Failure
+    );
+    assert_eq!(
+        backend.get_run_status(run.run_id).unwrap(),
+        RunStatus::Failed
+    );
+}
+
+pub fn retry_delay<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let (tasks, edges) = default_pipeline(TaskOptions {
+        max_attempts: 2,
+        retry_delay: Duration::from_secs(3600),
+        ..Default::default()
+    });
+    let mut backend = new_backend(&tasks, &edges, &PipelineOptions::default());
+    let run = backend.create_new_run(Utc::now(), None).unwrap();
+    backend.enqueue_run(&run, None).unwrap();
+
+    pop_and_finish(&mut backend, false, Value::Null);
+
+    // the retry is queued, but not popped before its delay has passed
+    assert_eq!(backend.get_queue_length().unwrap(), 1);
+    assert!(backend.pop_priority_queue().unwrap().is_none());
+    assert_eq!(
+        backend.get_task_status(run.run_id, 0).unwrap(),
+        TaskStatus::RetryPending
+    );
+}
+
+pub fn pipeline_retry_defaults<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let (mut tasks, edges) = default_pipeline(TaskOptions::default());
+    let own_policy = RetryPolicy::Exponential {
+        multiplier: 3.0,
+        max_delay: Duration::from_secs(60),
+    };
+    tasks[1].options.retry_policy = own_policy;
+    tasks[1].options.retry_jitter = Duration::from_secs(1);
+    let pipeline_policy = RetryPolicy::Exponential {
+        multiplier: 2.0,
+        max_delay: Duration::from_secs(3600),
+    };
+    let mut backend = new_backend(
+        &tasks,
+        &edges,
+        &PipelineOptions {
+            retry_policy: pipeline_policy,
+            retry_jitter: Duration::from_secs(5),
+            ..Default::default()
+        },
+    );
+    let run = backend.create_new_run(Utc::now(), None).unwrap();
+    backend.enqueue_run(&run, None).unwrap();
+
+    // tasks keep the retry options they set themselves
+    for (task_id, retry_policy, retry_jitter) in [
+        (0, pipeline_policy, Duration::from_secs(5)),
+        (1, own_policy, Duration::from_secs(1)),
+        (2, pipeline_policy, Duration::from_secs(5)),
+    ] {
+        let options = backend.get_task_by_id(run.run_id, task_id).unwrap().options;
+        assert_eq!(options.retry_policy, retry_policy);
+        assert_eq!(options.retry_jitter, retry_jitter);
+    }
+}
+
+const ALL_TASK_STATUSES: [TaskStatus; 7] = [
+    TaskStatus::Pending,
+    TaskStatus::Running,
+    TaskStatus::RetryPending,
+    TaskStatus::Success,
+    TaskStatus::Failure,
+    TaskStatus::Skipped,
+    TaskStatus::Cancelled,
+];
+
+pub fn trigger_rules<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let rules = [
+        TriggerRule::AllDone,
+        TriggerRule::AnyDone,
+        TriggerRule::AllSuccess,
+        TriggerRule::AnySuccess,
+        TriggerRule::AnyFailed,
+        TriggerRule::AllFailed,
+        TriggerRule::NoneFailed,
+        TriggerRule::NoneSkipped,
+        TriggerRule::AllSkipped,
+        TriggerRule::Always,
+        TriggerRule::NoneFailedMinOneSuccess,
+    ];
+
+    for trigger_rule in rules {
+        // left, right -> join
+        let tasks = vec![
+            task(0, "left", json!({}), TaskOptions::default()),
+            task(1, "right", json!({}), TaskOptions::default()),
+            task(
+                2,
+                "join",
+                json!({}),
+                TaskOptions {
+                    trigger_rule,
+                    ..Default::default()
+                },
+            ),
+        ];
+        let mut backend = new_backend(
+            &tasks,
+            &HashSet::from([(0, 2), (1, 2)]),
+            &PipelineOptions::default(),
+        );
+        let run = backend.create_new_run(Utc::now(), None).unwrap();
+        backend.enqueue_run(&run, None).unwrap();
+
+        // tasks without upstream tasks can always run
+        assert!(backend.trigger_rules_satisfied(run.run_id, 0).unwrap());
+
+        for left in &ALL_TASK_STATUSES {
+            for right in &ALL_TASK_STATUSES {
+                backend
+                    .set_task_status(run.run_id, 0, left.clone())
+                    .unwrap();
+                backend
+                    .set_task_status(run.run_id, 1, right.clone())
+                    .unwrap();
+                assert_eq!(
+                    backend.trigger_rules_satisfied(run.run_id, 2).unwrap(),
+                    trigger_rule.is_satisfied(&[left.clone(), right.clone()]),
+                    "{trigger_rule:?} with upstream {left:?}, {right:?}"
+                );
+            }
+        }
+    }
+
+    // a skipped upstream task doesn't skip a join that tolerates it
+    let tasks = vec![
+        task(0, "left", json!({}), TaskOptions::default()),
+        task(1, "right", json!({}), TaskOptions::default()),
+        task(
+            2,
+            "join",
+            json!({}),
+            TaskOptions {
+                trigger_rule: TriggerRule::NoneFailed,
+                ..Default::default()
+            },
+        ),
+    ];
+    let mut backend = new_backend(
+        &tasks,
+        &HashSet::from([(0, 2), (1, 2)]),
+        &PipelineOptions::default(),
+    );
+    let run = backend.create_new_run(Utc::now(), None).unwrap();
+    backend.enqueue_run(&run, None).unwrap();
+
+    let temp_queued_task = backend.pop_priority_queue().unwrap().unwrap();
+    let mut task_result = result_for(&tasks[0], 1, false, Value::Null);
+    task_result.skipped = true;
+    backend
+        .handle_task_result(run.run_id, &temp_queued_task.queued_task, task_result)
+        .unwrap();
+    backend.remove_from_temp_queue(&temp_queued_task).unwrap();
+    assert_eq!(
+        backend.get_task_status(run.run_id, 2).unwrap(),
+        TaskStatus::Pending
+    );
+
+    pop_and_finish(&mut backend, true, Value::Null);
+    pop_and_finish(&mut backend, true, Value::Null);
+    assert_eq!(
+        backend.get_task_status(run.run_id, 2).unwrap(),
+        TaskStatus::Success
+    );
+}
+
+pub fn switch<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    // choose -> a, b, c; b -> after_b
+    let mut tasks = vec![
+        task(0, "choose", json!({}), TaskOptions::default()),
+        task(1, "a", json!({}), TaskOptions::default()),
+        task(2, "b", json!({}), TaskOptions::default()),
+        task(3, "c", json!({}), TaskOptions::default()),
+        task(4, "after_b", json!({}), TaskOptions::default()),
+    ];
+    tasks[0].is_branch = true;
+    let mut backend = new_backend(
+        &tasks,
+        &HashSet::from([(0, 1), (0, 2), (0, 3), (2, 4)]),
+        &PipelineOptions::default(),
+    );
+    let run = backend.create_new_run(Utc::now(), None).unwrap();
+    backend.enqueue_run(&run, None).unwrap();
+
+    pop_and_finish(&mut backend, true, json!(Switch::new("b", 5)));
+
+    // the switch passes on only the value
+    assert_eq!(
+        backend.get_task_result(run.run_id, 0).unwrap().result,
+        json!(5)
+    );
+    for (task_id, status) in [
+        (1, TaskStatus::Skipped),
+        (2, TaskStatus::Pending),
+        (3, TaskStatus::Skipped),
+        (4, TaskStatus::Pending),
+    ] {
+        assert_eq!(
+            backend.get_task_status(run.run_id, task_id).unwrap(),
+            status
+        );
+    }
+    assert_eq!(
+        backend
+            .pop_priority_queue()
+            .unwrap()
+            .unwrap()
+            .queued_task
+            .task_id,
+        2
+    );
+    assert!(backend.pop_priority_queue().unwrap().is_none());
+}
+
+pub fn switch_in_task_group<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    // extract/choose -> extract/load, extract/notify, publish
+    let mut tasks = vec![
+        task(0, "extract/choose", json!({}), TaskOptions::default()),
+        task(1, "extract/load", json!({}), TaskOptions::default()),
+        task(2, "extract/notify", json!({}), TaskOptions::default()),
+        task(3, "publish", json!({}), TaskOptions::default()),
+    ];
+    tasks[0].is_branch = true;
+    let mut backend = new_backend(
+        &tasks,
+        &HashSet::from([(0, 1), (0, 2), (0, 3)]),
+        &PipelineOptions::default(),
+    );
+    let run = backend.create_new_run(Utc::now(), None).unwrap();
+    backend.enqueue_run(&run, None).unwrap();
+
+    // arms are named relative to the group of the switch
+    pop_and_finish(&mut backend, true, json!(Switch::new("load", Value::Null)));
+    for (task_id, status) in [
+        (1, TaskStatus::Pending),
+        (2, TaskStatus::Skipped),
+        (3, TaskStatus::Skipped),
+    ] {
+        assert_eq!(
+            backend.get_task_status(run.run_id, task_id).unwrap(),
+            status
+        );
+    }
+}
+
+pub fn branch_join<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    // choose -> a, b; a, b -> join, strict
+    let trigger_rule = |trigger_rule| TaskOptions {
+        trigger_rule,
+        ..Default::default()
+    };
+    let mut tasks = vec![
+        task(0, "choose", json!({}), TaskOptions::default()),
+        task(1, "a", json!({}), TaskOptions::default()),
+        task(2, "b", json!({}), TaskOptions::default()),
+        task(3, "join", json!({}), trigger_rule(TriggerRule::AnySuccess)),
+        task(
+            4,
+            "strict",
+            json!({}),
+            trigger_rule(TriggerRule::AllSuccess),
+        ),
+    ];
+    tasks[0].is_branch = true;
+    let mut backend = new_backend(
+        &tasks,
+        &HashSet::from([(0, 1), (0, 2), (1, 3), (2, 3), (1, 4), (2, 4)]),
+        &PipelineOptions::default(),
+    );
+    let run = backend.create_new_run(Utc::now(), None).unwrap();
+    backend.enqueue_run(&run, None).unwrap();
+
+    // skipping the other arm only skips the tasks that can't run anymore
+    pop_and_finish(&mut backend, true, json!(Switch::new("b", 5)));
+    for (task_id, status) in [
+        (1, TaskStatus::Skipped),
+        (2, TaskStatus::Pending),
+        (3, TaskStatus::Pending),
+        (4, TaskStatus::Skipped),
+    ] {
+        assert_eq!(
+            backend.get_task_status(run.run_id, task_id).unwrap(),
+            status
+        );
+    }
+
+    assert_eq!(pop_and_finish(&mut backend, true, Value::Null).task_id, 2);
+    assert_eq!(pop_and_finish(&mut backend, true, Value::Null).task_id, 3);
+    assert!(backend.pop_priority_queue().unwrap().is_none());
+    assert_eq!(
+        backend.get_run_status(run.run_id).unwrap(),
+        RunStatus::Success
+    );
+}
+
+pub fn when_condition<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    // check -> load, load -> after
+    let tasks = vec![
+        task(0, "check", json!({}), TaskOptions::default()),
+        task(
+            1,
+            "load",
+            json!({}),
+            TaskOptions {
+                when: Some("{{check.count}} > 0".into()),
+                ..Default::default()
+            },
+        ),
+        task(2, "after", json!({}), TaskOptions::default()),
+    ];
+    let mut backend = new_backend(
+        &tasks,
+        &HashSet::from([(0, 1), (1, 2)]),
+        &PipelineOptions::default(),
+    );
+    let run = backend.create_new_run(Utc::now(), None).unwrap();
+    backend.enqueue_run(&run, None).unwrap();
+
+    pop_and_finish(&mut backend, true, json!({ "count": 0 }));
+    let queued_task = QueuedTask {
+        task_id: 1,
+        run_id: run.run_id,
+        pipeline_name: run.pipeline_name.clone(),
+        scheduled_date_for_run: run.scheduled_date_for_run,
+        attempt: 1,
+        not_before: None,
+        task_name: tasks[1].name.clone(),
+        max_concurrency: None,
+        max_active_tasks: None,
+        pool: None,
+        pool_slots: 1,
+        priority_weight: 1,
+        queue: tasks[1].options.queue.clone(),
+    };
+    let run_context = backend.get_run_context(&queued_task, &tasks[1]).unwrap();
+    assert!(!backend.is_condition_met(&run_context, &tasks[1]).unwrap());
+    // conditions can only read upstream tasks
+    let mut not_upstream = tasks[1].clone();
+    not_upstream.options.when = Some("{{after}} == null".into());
+    assert!(backend
+        .is_condition_met(&run_context, &not_upstream)
+        .is_err());
+    backend
+        .insert_task_results(
+            run.run_id,
+            &result_for(&tasks[0], 1, true, json!({ "count": 2 })),
+        )
+        .unwrap();
+    assert!(backend.is_condition_met(&run_context, &tasks[1]).unwrap());
+    backend
+        .insert_task_results(
+            run.run_id,
+            &result_for(&tasks[0], 1, true, json!({ "count": 0 })),
+        )
+        .unwrap();
+
+    // the task is skipped without being run
+    let temp_queued_task = backend.pop_priority_queue().unwrap().unwrap();
+    assert_eq!(temp_queued_task.queued_task.task_id, 1);
+    backend.work(&temp_queued_task, "").unwrap();
+    assert_eq!(
+        backend.get_task_status(run.run_id, 1).unwrap(),
+        TaskStatus::Skipped
+    );
+    assert_eq!(
+        backend.get_task_status(run.run_id, 2).unwrap(),
+        TaskStatus::Skipped
+    );
+}
+
+pub fn when_skipped_upstream<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    // choose -> a, b; a, b -> load
+    let mut tasks = vec![
+        task(0, "choose", json!({}), TaskOptions::default()),
+        task(1, "a", json!({}), TaskOptions::default()),
+        task(2, "b", json!({}), TaskOptions::default()),
+        task(
+            3,
+            "load",
+            json!({}),
+            TaskOptions {
+                when: Some("{{b.count}} > 0".into()),
+                trigger_rule: TriggerRule::AllDone,
+                ..Default::default()
+            },
+        ),
+    ];
+    tasks[0].is_branch = true;
+    let mut backend = new_backend(
+        &tasks,
+        &HashSet::from([(0, 1), (0, 2), (1, 3), (2, 3)]),
+        &PipelineOptions::default(),
+    );
+    let run = backend.create_new_run(Utc::now(), None).unwrap();
+    backend.enqueue_run(&run, None).unwrap();
+
+    pop_and_finish(&mut backend, true, json!(Switch::new("b", Value::Null)));
+    assert_eq!(
+        backend.get_task_status(run.run_id, 1).unwrap(),
+        TaskStatus::Skipped
+    );
+    assert!(backend.get_task_result(run.run_id, 1).is_err());
+    assert_eq!(
+        pop_and_finish(&mut backend, true, json!({ "count": 0 })).task_id,
+        2
+    );
+
+    // the skipped upstream task has no result, which the condition doesn't need
+    let temp_queued_task = backend.pop_priority_queue().unwrap().unwrap();
+    assert_eq!(temp_queued_task.queued_task.task_id, 3);
+    let run_context = backend
+        .get_run_context(&temp_queued_task.queued_task, &tasks[3])
+        .unwrap();
+    assert!(!backend.is_condition_met(&run_context, &tasks[3]).unwrap());
+    backend.work(&temp_queued_task, "").unwrap();
+    assert_eq!(
+        backend.get_task_status(run.run_id, 3).unwrap(),
+        TaskStatus::Skipped
+    );
+}
+
+pub fn template_expressions<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let (tasks, edges) = default_pipeline(TaskOptions::default());
+    let mut backend = new_backend(&tasks, &edges, &PipelineOptions::default());
+    let run = backend.create_new_run(Utc::now(), None).unwrap();
+    backend.enqueue_run(&run, None).unwrap();
+    let temp_queued_task = backend.pop_priority_queue().unwrap().unwrap();
+    let task = backend
+        .get_task_by_id(run.run_id, temp_queued_task.queued_task.task_id)
+        .unwrap();
+    let run_context = backend
+        .get_run_context(&temp_queued_task.queued_task, &task)
+        .unwrap();
+    assert_eq!(run_context.run_id, run.run_id);
+    assert_eq!(run_context.task_name, task.name);
+    assert_eq!(run_context.attempt, 1);
+    assert_eq!(run_context.scheduled_date, run.scheduled_date_for_run);
+
+    let rendered = backend
+        .render_templates(
+            &run_context,
+            json!({
+                "script": "echo {{extract.files | join: ' '}} {{ run_id }}",
+                "{{extract.files | join: ' '}}": { "files": ["a", "b"] },
+                "{{ run_id }}": null,
+                "{{task_name | upper}}": null,
+                "{{extract.missing | default: 0}}": {},
+            }),
+        )
+        .unwrap();
+    assert_eq!(
+        rendered,
+        json!({
+            "script": "echo {{extract.files | join: ' '}} {{ run_id }}",
+            "{{extract.files | join: ' '}}": "a b",
+            "{{ run_id }}": run.run_id,
+            "{{task_name | upper}}": task.name.to_uppercase(),
+            "{{extract.missing | default: 0}}": 0,
+        })
+    );
+    assert!(backend
+        .render_templates(&run_context, json!({ "{{extract.missing}}": {} }))
+        .is_err());
+}
+
+pub fn data_interval<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let tasks = vec![task(0, "load", json!({}), TaskOptions::default())];
+    let mut backend = new_backend(&tasks, &HashSet::new(), &PipelineOptions::default());
+    let get_data_interval = |backend: &mut B| {
+        let temp_queued_task = backend.pop_priority_queue().unwrap().unwrap();
+        let task = backend
+            .get_task_by_id(
+                temp_queued_task.queued_task.run_id,
+                temp_queued_task.queued_task.task_id,
+            )
+            .unwrap();
+        let run_context = backend
+            .get_run_context(&temp_queued_task.queued_task, &task)
+            .unwrap();
+        backend.remove_from_temp_queue(&temp_queued_task).unwrap();
+        DataInterval {
+            start: run_context.data_interval_start,
+            end: run_context.data_interval_end,
+        }
+    };
+
+    // scheduled runs process the interval between two ticks
+    let now = Utc::now();
+    let scheduled = DataInterval {
+        start: now - chrono::Duration::hours(1),
+        end: now,
+    };
+    let run = backend
+        .create_new_run(now, Some(scheduled.clone()))
+        .unwrap();
+    assert_eq!(run.data_interval, Some(scheduled.clone()));
+    backend.enqueue_run(&run, None).unwrap();
+    assert_eq!(
+        backend.get_data_interval(run.run_id).unwrap(),
+        Some(scheduled.clone())
+    );
+    assert_eq!(get_data_interval(&mut backend), scheduled);
+
+    // manual runs have an empty interval at their scheduled date
+    let run = backend.create_new_run(now, None).unwrap();
+    backend.enqueue_run(&run, None).unwrap();
+    assert_eq!(
+        get_data_interval(&mut backend),
+        DataInterval {
+            start: now,
+            end: now,
+        }
+    );
+}
+
+pub fn trigger_params<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    // no task opts into the params, they are still part of the run context
+    let tasks = vec![task(0, "load", json!({}), TaskOptions::default())];
+    let mut backend = new_backend(&tasks, &HashSet::new(), &PipelineOptions::default());
+    let mut get_params = |params: Option<Value>| {
+        let run = backend.create_new_run(Utc::now(), None).unwrap();
+        backend.enqueue_run(&run, params).unwrap();
+        let temp_queued_task = backend.pop_priority_queue().unwrap().unwrap();
+        let task = backend.get_task_by_id(run.run_id, 0).unwrap();
+        let run_context = backend
+            .get_run_context(&temp_queued_task.queued_task, &task)
+            .unwrap();
+        backend.remove_from_temp_queue(&temp_queued_task).unwrap();
+        assert_eq!(
+            backend.get_trigger_params(run.run_id).unwrap(),
+            run_context.params
+        );
+        run_context.params
+    };
+
+    assert_eq!(
+        get_params(Some(json!({ "date": "2024-01-01" }))),
+        json!({ "date": "2024-01-01" })
+    );
+    assert_eq!(get_params(None), Value::Null);
+}
+
+pub fn active_runs<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let tasks = vec![task(0, "load", json!({}), TaskOptions::default())];
+    let mut backend = new_backend(&tasks, &HashSet::new(), &PipelineOptions::default());
+    assert_eq!(backend.get_active_runs_count().unwrap(), 0);
+
+    // runs are active from when they are created until they finish
+    let first = backend.create_new_run(Utc::now(), None).unwrap();
+    assert_eq!(backend.get_active_runs_count().unwrap(), 1);
+    backend.enqueue_run(&first, None).unwrap();
+    let second = backend.create_new_run(Utc::now(), None).unwrap();
+    backend.enqueue_run(&second, None).unwrap();
+    assert_eq!(backend.get_active_runs_count().unwrap(), 2);
+
+    pop_and_finish(&mut backend, true, Value::Null);
+    assert_eq!(backend.get_active_runs_count().unwrap(), 1);
+    pop_and_finish(&mut backend, false, Value::Null);
+    assert_eq!(backend.get_active_runs_count().unwrap(), 0);
+    assert!(backend.get_active_run_ids().unwrap().is_empty());
+
+    // a cleared run is active again
+    backend.clear_task(&first, 0, false).unwrap();
+    assert_eq!(backend.get_active_runs_count().unwrap(), 1);
+    pop_and_finish(&mut backend, true, Value::Null);
+    assert_eq!(backend.get_active_runs_count().unwrap(), 0);
+
+    // runs are only created below the limit, and finished runs don't count towards it
+    let limited = backend
+        .try_create_new_run(Utc::now(), None, Some(1))
+        .unwrap()
+        .unwrap();
+    assert!(backend
+        .create_new_run_below(Utc::now(), None, 1)
+        .unwrap()
+        .is_none());
+    assert!(backend
+        .try_create_new_run(Utc::now(), None, Some(1))
+        .unwrap()
+        .is_none());
+    assert_eq!(backend.get_active_run_ids().unwrap(), vec![limited.run_id]);
+    backend.enqueue_run(&limited, None).unwrap();
+    pop_and_finish(&mut backend, true, Value::Null);
+    let finished = backend
+        .try_create_new_run(Utc::now(), None, Some(1))
+        .unwrap()
+        .unwrap();
+    backend.enqueue_run(&finished, None).unwrap();
+    pop_and_finish(&mut backend, true, Value::Null);
+
+    // a run that fails to enqueue gives up its slot
+    let tasks = vec![task(1, "misnumbered", json!({}), TaskOptions::default())];
+    let mut backend = new_backend(&tasks, &HashSet::new(), &PipelineOptions::default());
+    let broken = backend
+        .try_create_new_run(Utc::now(), None, Some(1))
+        .unwrap()
+        .unwrap();
+    assert!(backend.enqueue_run(&broken, None).is_err());
+    assert!(backend.get_active_run_ids().unwrap().is_empty());
+    assert!(backend
+        .try_create_new_run(Utc::now(), None, Some(1))
+        .unwrap()
+        .is_some());
+
+    // a run stays active after a failure while its other tasks can still run, but not once
+    // its remaining tasks wait on the failed one
+    // load, other -> after
+    let tasks = vec![
+        task(0, "load", json!({}), TaskOptions::default()),
+        task(1, "other", json!({}), TaskOptions::default()),
+        task(
+            2,
+            "after",
+            json!({}),
+            TaskOptions {
+                trigger_rule: TriggerRule::AllSuccess,
+                ..Default::default()
+            },
+        ),
+    ];
+    let mut backend = new_backend(
+        &tasks,
+        &HashSet::from([(0, 2), (1, 2)]),
+        &PipelineOptions::default(),
+    );
+    let run = backend.create_new_run(Utc::now(), None).unwrap();
+    backend.enqueue_run(&run, None).unwrap();
+    assert_eq!(pop_and_finish(&mut backend, false, Value::Null).task_id, 0);
+    assert_eq!(
+        backend.get_run_status(run.run_id).unwrap(),
+        RunStatus::Failed
+    );
+    assert_eq!(backend.get_active_runs_count().unwrap(), 1);
+    pop_and_finish(&mut backend, true, Value::Null);
+    assert_eq!(
+        backend.get_task_status(run.run_id, 2).unwrap(),
+        TaskStatus::Pending
+    );
+    assert_eq!(backend.get_active_runs_count().unwrap(), 0);
+}
+
+pub fn max_active_tasks<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let tasks = vec![
+        task(0, "load", json!({}), TaskOptions::default()),
+        task(1, "other", json!({}), TaskOptions::default()),
+    ];
+    let mut backend = new_backend(
+        &tasks,
+        &HashSet::new(),
+        &PipelineOptions {
+            max_active_tasks: Some(1),
+            ..Default::default()
+        },
+    );
+    let run = backend.create_new_run(Utc::now(), None).unwrap();
+    backend.enqueue_run(&run, None).unwrap();
+
+    // the pipeline runs one task at a time
+    let load = backend.pop_priority_queue().unwrap().unwrap();
+    assert_eq!(load.queued_task.max_active_tasks, Some(1));
+    assert!(backend.pop_priority_queue().unwrap().is_none());
+
+    backend.remove_from_temp_queue(&load).unwrap();
+    let other = backend.pop_priority_queue().unwrap().unwrap();
+    assert_ne!(other.queued_task.task_id, load.queued_task.task_id);
+}
+
+pub fn max_concurrency<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let tasks = vec![
+        task(
+            0,
+            "load",
+            json!({}),
+            TaskOptions {
+                max_concurrency: Some(1),
+                ..Default::default()
+            },
+        ),
+        task(1, "other", json!({}), TaskOptions::default()),
+    ];
+    let mut backend = new_backend(&tasks, &HashSet::new(), &PipelineOptions::default());
+    for _ in 0..2 {
+        let run = backend.create_new_run(Utc::now(), None).unwrap();
+        backend.enqueue_run(&run, None).unwrap();
+    }
+
+    // the second load waits for the first, without holding back the other tasks
+    let mut popped = vec![];
+    while let Some(temp_queued_task) = backend.pop_priority_queue().unwrap() {
+        popped.push(temp_queued_task);
+    }
+    let mut names: Vec<&str> = popped
+        .iter()
+        .map(|t| t.queued_task.task_name.as_str())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["load", "other", "other"]);
+
+    let load = popped
+        .iter()
+        .find(|t| t.queued_task.task_name == "load")
+        .unwrap();
+    backend.remove_from_temp_queue(load).unwrap();
+    let temp_queued_task = backend.pop_priority_queue().unwrap().unwrap();
+    assert_eq!(temp_queued_task.queued_task.task_name, "load");
+    assert_ne!(temp_queued_task.queued_task.run_id, load.queued_task.run_id);
+    assert!(backend.pop_priority_queue().unwrap().is_none());
+}
+
+pub fn resource_pools<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let pool_options = |pool: &str, pool_slots| TaskOptions {
+        pool: Some(pool.into()),
+        pool_slots,
+        ..Default::default()
+    };
+    let tasks = vec![
+        task(0, "big", json!({}), pool_options("warehouse", 2)),
+        task(1, "small", json!({}), pool_options("warehouse", 1)),
+        task(2, "undefined", json!({}), pool_options("undefined", 5)),
+    ];
+    let mut backend = new_backend(&tasks, &HashSet::new(), &PipelineOptions::default());
+    assert!(backend.get_resource_pools().unwrap().is_empty());
+
+    let warehouse = ResourcePool {
+        slots: 3,
+        description: "connections to the warehouse".into(),
+    };
+    backend.set_resource_pool("warehouse", &warehouse).unwrap();
+    assert_eq!(
+        backend.get_resource_pools().unwrap(),
+        [("warehouse".to_string(), warehouse)].into()
+    );
+    for _ in 0..2 {
+        let run = backend.create_new_run(Utc::now(), None).unwrap();
+        backend.enqueue_run(&run, None).unwrap();
+    }
+
+    let pop_all = |backend: &mut B| {
+        let mut popped = vec![];
+        while let Some(temp_queued_task) = backend.pop_priority_queue().unwrap() {
+            popped.push(temp_queued_task);
+        }
+        popped
+    };
+    let task_names = |popped: &[TempQueuedTask]| {
+        popped
+            .iter()
+            .map(|t| t.queued_task.task_name.clone())
+            .collect::<Vec<String>>()
+    };
+
+    // 3 slots fit one big and one small task, and pools that aren't defined don't limit
+    let popped = pop_all(&mut backend);
+    assert_eq!(
+        task_names(&popped),
+        vec!["big", "small", "undefined", "undefined"]
+    );
+
+    // freeing slots lets the remaining tasks through
+    for temp_queued_task in &popped {
+        backend.remove_from_temp_queue(temp_queued_task).unwrap();
+    }
+    assert_eq!(task_names(&pop_all(&mut backend)), vec!["big", "small"]);
+
+    backend.delete_resource_pool("warehouse").unwrap();
+    assert!(backend.get_resource_pools().unwrap().is_empty());
+}
+
+pub fn exit_codes<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let (tasks, edges) = default_pipeline(TaskOptions {
+        max_attempts: 3,
+        retry_exit_codes: Some(vec![75]),
+        skip_exit_code: Some(99),
+        ..Default::default()
+    });
+    let mut backend = new_backend(&tasks, &edges, &PipelineOptions::default());
+    let handle_exit_code = |backend: &mut B, run_id: usize, exit_code: i32| {
+        let temp_queued_task = backend.pop_priority_queue().unwrap().unwrap();
+        let queued_task = &temp_queued_task.queued_task;
+        let task = backend.get_task_by_id(run_id, queued_task.task_id).unwrap();
+        let mut task_result = result_for(&task, queued_task.attempt, false, Value::Null);
+        task_result.exit_code = Some(exit_code);
+        task_result.skipped = task.options.is_skip_exit_code(Some(exit_code));
+        backend
+            .handle_task_result(run_id, queued_task, task_result)
+            .unwrap();
+        backend.remove_from_temp_queue(&temp_queued_task).unwrap();
+    };
+
+    // only retryable exit codes are retried
+    let run = backend.create_new_run(Utc::now(), None).unwrap();
+    backend.enqueue_run(&run, None).unwrap();
+    handle_exit_code(&mut backend, run.run_id, 75);
+    assert_eq!(
+        backend.get_task_status(run.run_id, 0).unwrap(),
+        TaskStatus::RetryPending
+    );
+    handle_exit_code(&mut backend, run.run_id, 1);
+    assert_eq!(
+        backend.get_task_status(run.run_id, 0).unwrap(),
+        TaskStatus::Failure
+    );
+
+    // the skip exit code skips the task and everything downstream of it
+    let mut backend = new_backend(&tasks, &edges, &PipelineOptions::default());
+    let run = backend.create_new_run(Utc::now(), None).unwrap();
+    backend.enqueue_run(&run, None).unwrap();
+    handle_exit_code(&mut backend, run.run_id, 99);
+    for task_id in 0..3 {
+        assert_eq!(
+            backend.get_task_status(run.run_id, task_id).unwrap(),
+            TaskStatus::Skipped
+        );
+    }
+    assert!(backend.pop_priority_queue().unwrap().is_none());
+    assert_eq!(
+        backend.get_run_status(run.run_id).unwrap(),
+        RunStatus::Success
+    );
+}
+
+pub fn delayed_tasks<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let (mut backend, run_id) = start_run(new_backend);
+    let pipeline_name = backend.get_pipeline_name().unwrap();
+    let now = Utc::now();
+
+    // the root task is deferred, while a deeper task is already due
+    backend
+        .enqueue_task(
+            run_id,
+            0,
+            now,
+            pipeline_name.clone(),
+            false,
+            Some(now + chrono::Duration::hours(1)),
+        )
+        .unwrap();
+    backend
+        .enqueue_task(
+            run_id,
+            2,
+            now,
+            pipeline_name.clone(),
+            false,
+            Some(now - chrono::Duration::seconds(1)),
+        )
+        .unwrap();
+    assert_eq!(backend.get_queue_length().unwrap(), 2);
+
+    let due = backend.pop_priority_queue().unwrap().unwrap();
+    assert_eq!(due.queued_task.task_id, 2);
+    backend.remove_from_temp_queue(&due).unwrap();
+
+    // entries that are not due stay in the queue
+    assert!(backend.pop_priority_queue().unwrap().is_none());
+    assert_eq!(backend.get_queue_length().unwrap(), 1);
+
+    backend
+        .enqueue_task(run_id, 0, now, pipeline_name, false, None)
+        .unwrap();
+    let popped = backend.pop_priority_queue().unwrap().unwrap();
+    assert_eq!(popped.queued_task.task_id, 0);
+    assert_eq!(popped.queued_task.not_before, None);
+}
+
+pub fn concurrent_runs<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let (mut backend, first_run_id) = start_run(new_backend);
+    let second_run = backend.create_new_run(Utc::now(), None).unwrap();
+    assert_ne!(first_run_id, second_run.run_id);
+    backend.enqueue_run(&second_run, None).unwrap();
+
+    // both runs have their own root task queued
+    assert_eq!(backend.get_queue_length().unwrap(), 2);
+    assert_eq!(backend.get_all_tasks(second_run.run_id).unwrap().len(), 3);
+
+    backend
+        .set_task_status(first_run_id, 2, TaskStatus::Skipped)
+        .unwrap();
+    backend
+        .set_template_args(first_run_id, 2, &json!({"a": 1}).to_string())
+        .unwrap();
+    backend.remove_edge(first_run_id, (1, 2)).unwrap();
+    assert_eq!(
+        backend.get_task_status(second_run.run_id, 2).unwrap(),
+        TaskStatus::Pending
+    );
+    assert_eq!(
+        backend.get_template_args(second_run.run_id, 2).unwrap(),
+        json!({})
+    );
+    assert_eq!(backend.get_upstream(second_run.run_id, 2).unwrap(), vec![1]);
+
+    // finishing one run leaves the other untouched
+    for _ in 0..2 {
+        let temp_queued_task = backend.pop_priority_queue().unwrap().unwrap();
+        let queued_task = &temp_queued_task.queued_task;
+        assert_eq!(queued_task.attempt, 1);
+        let task = backend
+            .get_task_by_id(queued_task.run_id, queued_task.task_id)
+            .unwrap();
+        let success = queued_task.run_id == second_run.run_id;
+        backend
+            .handle_task_result(
+                queued_task.run_id,
+                queued_task,
+                result_for(&task, queued_task.attempt, success, json!(success)),
+            )
+            .unwrap();
+        backend.remove_from_temp_queue(&temp_queued_task).unwrap();
+    }
+
+    assert_eq!(
+        backend.get_run_status(first_run_id).unwrap(),
+        RunStatus::Failed
+    );
+    assert_eq!(
+        backend.get_run_status(second_run.run_id).unwrap(),
+        RunStatus::Running
+    );
+    assert_eq!(
+        backend
+            .get_task_result(second_run.run_id, 0)
+            .unwrap()
+            .result,
+        json!(true)
+    );
+}
+
+pub fn cancel_run<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let (mut backend, run_id) = start_run(new_backend);
+    let other_run = backend.create_new_run(Utc::now(), None).unwrap();
+    backend.enqueue_run(&other_run, None).unwrap();
+
+    pop_and_finish(&mut backend, true, json!("hello"));
+    let get_status = backend.get_task_status_closure(run_id, 1).unwrap();
+    assert_eq!(get_status().unwrap(), TaskStatus::Pending);
+
+    backend.cancel_run(run_id).unwrap();
+
+    // finished tasks keep their status
+    assert_eq!(
+        backend.get_task_status(run_id, 0).unwrap(),
+        TaskStatus::Success
+    );
+    assert_eq!(get_status().unwrap(), TaskStatus::Cancelled);
+    assert_eq!(
+        backend.get_task_status(run_id, 2).unwrap(),
+        TaskStatus::Cancelled
+    );
+    assert_eq!(
+        backend.get_run_status(run_id).unwrap(),
+        RunStatus::Cancelled
+    );
+
+    // only the other run is left in the queue
+    assert_eq!(backend.get_queue_length().unwrap(), 1);
+    let temp_queued_task = backend.pop_priority_queue().unwrap().unwrap();
+    assert_eq!(temp_queued_task.queued_task.run_id, other_run.run_id);
+    assert_eq!(
+        backend.get_run_status(other_run.run_id).unwrap(),
+        RunStatus::Pending
+    );
+}
+
+pub fn clear_task<B: Backend + Send + Sync>(new_backend: NewBackend<B>) {
+    let (tasks, edges) = default_pipeline(TaskOptions::default());
+    let mut backend = new_backend(&tasks, &edges, &PipelineOptions::default());
+    let run = backend.create_new_run(Utc::now(), None).unwrap();
+    backend.enqueue_run(&run, None).unwrap();
+
+    pop_and_finish(&mut backend, true, json!("hello"));
+    pop_and_finish(&mut backend, false, Value::Null);
+    assert_eq!(
+        backend.get_run_status(run.run_id).unwrap(),
+        RunStatus::Failed
+    );
+
+    backend.clear_task(&run, 1, true).unwrap();
+    for task_id in [1, 2] {
+        assert_eq!(
+            backend.get_task_status(run.run_id, task_id).unwrap(),
+            TaskStatus::Pending
+        );
+    }
+    assert_eq!(
+        backend.get_task_status(run.run_id, 0).unwrap(),
+        TaskStatus::Success
+    );
+
+    // the cleared task keeps counting attempts and reuses the upstream result
+    let consume = pop_and_finish(&mut backend, true, json!("hello"));
+    assert_eq!((consume.task_id, consume.attempt), (1, 2));
+    assert_eq!(pop_and_finish(&mut backend, true, Value::Null).task_id, 2);
+    assert_eq!(
+        backend.get_run_status(run.run_id).unwrap(),
+        RunStatus::Success
+    );
+
+    // a cleared task gets its full attempts again and keeps the logs of earlier attempts
+    let (tasks, edges) = default_pipeline(TaskOptions {
+        max_attempts: 2,
+        ..Default::default()
+    });
+    let mut backend = new_backend(&tasks, &edges, &PipelineOptions::default());
+    let run = backend.create_new_run(Utc::now(), None).unwrap();
+    backend.enqueue_run(&run, None).unwrap();
+    pop_and_finish(&mut backend, true, json!("hello"));
+    let log = backend.get_log_handle_closure(run.run_id, 1, 1).unwrap();
+    log("first attempt\n".to_string()).unwrap();
+    pop_and_finish(&mut backend, false, Value::Null);
+    pop_and_finish(&mut backend, false, Value::Null);
+    assert_eq!(
+        backend.get_task_status(run.run_id, 1).unwrap(),
+        TaskStatus::Failure
+    );
+
+    backend.clear_task(&run, 1, false).unwrap();
+    let consume = pop_and_finish(&mut backend, false, Value::Null);
+    assert_eq!((consume.attempt, consume.max_attempts), (3, 4));
+    assert_eq!(
+        backend.get_task_status(run.run_id, 1).unwrap(),
+        TaskStatus::RetryPending
+    );
+    assert_eq!(pop_and_finish(&mut backend, true, Value::Null).attempt, 4);
+    assert_eq!(
+        backend.get_log(run.run_id, 1, 1).unwrap(),
+        "first attempt\n"
+    );
+}
@@ -6,39 +6,61 @@ use std::{
 use chrono::{DateTime, Utc};
 use serde_json::{json, Value};
 use thepipelinetool_task::{
-    queued_task::QueuedTask, task_ref_inner::TaskRefInner, task_result::TaskResult,
-    task_status::TaskStatus, temp_queued_task::TempQueuedTask, trigger_rule::TriggerRule, Task,
+    branch::ChosenArm,
+    condition::evaluate_condition,
+    ordered_queued_task::MAX_PRIORITY_WEIGHT,
+    queued_task::QueuedTask,
+    run_context::RunContext,
+    task_ref_inner::TaskRefInner,
+    task_result::TaskResult,
+    task_status::TaskStatus,
+    temp_queued_task::TempQueuedTask,
+    template::{get_placeholder_expression, Expression, RUN_CONTEXT_NAMES},
+    weight_rule::WeightRule,
+    Task,
 };
 use thepipelinetool_utils::{
     collector, function_name_as_string, UPSTREAM_TASK_ID_KEY, UPSTREAM_TASK_RESULT_KEY,
 };
 
 use crate::{
-    run::{Run, RunStatus},
+    run::{DataInterval, Run, RunStatus},
     Backend,
 };
 use anyhow::Result;
 
 pub trait BlanketBackend {
     fn trigger_rules_satisfied(&mut self, run_id: usize, task_id: usize) -> Result<bool>;
+    fn get_upstream_statuses(&mut self, run_id: usize, task_id: usize) -> Result<Vec<TaskStatus>>;
+    fn skip_task(&mut self, run_id: usize, task_id: usize, queued_task: &QueuedTask) -> Result<()>;
 
     fn get_run_status(&mut self, run_id: usize) -> Result<RunStatus>;
+    fn get_active_runs_count(&mut self) -> Result<usize>;
+    fn is_run_active(&mut self, run_id: usize) -> Result<bool>;
+    fn try_create_new_run(
+        &mut self,
+        scheduled_date_for_run: DateTime<Utc>,
+        data_interval: Option<DataInterval>,
+        max_active_runs: Option<usize>,
+    ) -> Result<Option<Run>>;
+
+    fn get_priority_weight(&mut self, run_id: usize, task_id: usize) -> Result<usize>;
 
     fn is_task_done(&mut self, run_id: usize, task_id: usize) -> Result<bool>;
     fn task_needs_running(&mut self, run_id: usize, task_id: usize) -> Result<bool>;
     fn enqueue_run(&mut self, run: &Run, trigger_params: Option<Value>) -> Result<()>;
+    fn cancel_run(&mut self, run_id: usize) -> Result<()>;
+    fn clear_task(&mut self, run: &Run, task_id: usize, include_downstream: bool) -> Result<()>;
     // TODO move tpt_path into OrderedQueuedTask?
     fn work<D: AsRef<OsStr>>(&mut self, queued_task: &TempQueuedTask, tpt_path: D) -> Result<()>;
     fn update_referenced_dependencies(&mut self, run_id: usize, downstream_id: usize)
         -> Result<()>;
     fn run_task<D: AsRef<OsStr>>(
         &mut self,
-        run_id: usize,
         task: &Task,
-        attempt: usize,
         resolution_result: &Value,
         tpt_path: D,
-        scheduled_date_for_run: DateTime<Utc>,
+        run_context: &RunContext,
     ) -> Result<TaskResult>;
     fn resolve_args(
         &mut self,
@@ -46,6 +68,10 @@ pub trait BlanketBackend {
         template_args: &Value,
         upstream_deps: &HashMap<(usize, String), String>,
     ) -> Result<Value>;
+    fn get_run_context(&mut self, queued_task: &QueuedTask, task: &Task) -> Result<RunContext>;
+    fn render_templates(&mut self, run_context: &RunContext, resolved_args: Value)
+        -> Result<Value>;
+    fn is_condition_met(&mut self, run_context: &RunContext, task: &Task) -> Result<bool>;
 
     fn handle_task_result(
         &mut self,
@@ -58,20 +84,24 @@ pub trait BlanketBackend {
 impl<U: Backend + Send + Sync> BlanketBackend for U {
     fn get_run_status(&mut self, run_id: usize) -> Result<RunStatus> {
         let mut pending_count = 0;
+        let mut failed = false;
         let tasks = self.get_all_tasks(run_id)?;
 
         for task in &tasks {
             let status = self.get_task_status(run_id, task.id)?;
 
             match status {
-                TaskStatus::Failure => return Ok(RunStatus::Failed),
+                TaskStatus::Cancelled => return Ok(RunStatus::Cancelled),
+                TaskStatus::Failure => failed = true,
                 TaskStatus::Pending | TaskStatus::RetryPending => {
                     pending_count += 1;
                 }
                 _ => {}
             };
         }
-        if pending_count == tasks.len() {
+        if failed {
+            Ok(RunStatus::Failed)
+        } else if pending_count == tasks.len() {
             Ok(RunStatus::Pending)
         } else if pending_count > 0 {
             Ok(RunStatus::Running)
@@ -79,6 +109,93 @@ impl<U: Backend + Send + Sync> BlanketBackend for U {
             Ok(RunStatus::Success)
         }
     }
+
+    fn get_active_runs_count(&mut self) -> Result<usize> {
+        let mut count = 0;
+
+        for run_id in self.get_active_run_ids()? {
+            if self.is_run_active(run_id)? {
+                count += 1;
+            } else {
+                self.remove_active_run_id(run_id)?;
+            }
+        }
+        Ok(count)
+    }
+
+    /// A run is active while any of its tasks is waiting for a retry or can run, even after
+    /// another one failed. Pending tasks whose upstream tasks can't finish don't count.
+    fn is_run_active(&mut self, run_id: usize) -> Result<bool> {
+        let tasks = self.get_all_tasks(run_id)?;
+        // the run was created and its tasks are being enqueued
+        if tasks.is_empty() {
+            return Ok(true);
+        }
+        for task in tasks {
+            match self.get_task_status(run_id, task.id)? {
+                TaskStatus::Running | TaskStatus::RetryPending => return Ok(true),
+                TaskStatus::Pending if self.trigger_rules_satisfied(run_id, task.id)? => {
+                    return Ok(true);
+                }
+                _ => {}
+            }
+        }
+        Ok(false)
+    }
+
+    /// Creates a run unless `max_active_runs` runs are already active. Finished runs are pruned
+    /// first, so they don't count towards the limit.
+    fn try_create_new_run(
+        &mut self,
+        scheduled_date_for_run: DateTime<Utc>,
+        data_interval: Option<DataInterval>,
+        max_active_runs: Option<usize>,
+    ) -> Result<Option<Run>> {
+        let Some(max_active_runs) = max_active_runs else {
+            return Ok(Some(
+                self.create_new_run(scheduled_date_for_run, data_interval)?,
+            ));
+        };
+        self.get_active_runs_count()?;
+        self.create_new_run_below(scheduled_date_for_run, data_interval, max_active_runs)
+    }
+
+    fn get_priority_weight(&mut self, run_id: usize, task_id: usize) -> Result<usize> {
+        let task = self.get_task_by_id(run_id, task_id)?;
+        let mut priority_weight = task.options.priority_weight.min(MAX_PRIORITY_WEIGHT);
+        if task.options.weight_rule == WeightRule::Absolute {
+            return Ok(priority_weight);
+        }
+        // the graph is walked once per task, not on every retry
+        if let Some(priority_weight) = self.get_cached_priority_weight(run_id, task_id)? {
+            return Ok(priority_weight);
+        }
+
+        // every related task counts once, however many paths lead to it
+        let mut visited = HashSet::from([task_id]);
+        let mut to_visit = vec![task_id];
+        while let Some(task_id) = to_visit.pop() {
+            let related = match task.options.weight_rule {
+                WeightRule::Downstream => self.get_downstream(run_id, task_id)?,
+                _ => self.get_upstream(run_id, task_id)?,
+            };
+            for related_id in related {
+                if visited.insert(related_id) {
+                    priority_weight = priority_weight
+                        .saturating_add(
+                            self.get_task_by_id(run_id, related_id)?
+                                .options
+                                .priority_weight,
+                        )
+                        .min(MAX_PRIORITY_WEIGHT);
+                    to_visit.push(related_id);
+                }
+            }
+        }
+        self.set_cached_priority_weight(run_id, task_id, priority_weight)?;
+        Ok(priority_weight)
+    }
+
     fn trigger_rules_satisfied(&mut self, run_id: usize, task_id: usize) -> Result<bool> {
         let task = self.get_task_by_id(run_id, task_id)?;
 
@@ -95,80 +212,67 @@ impl<U: Backend + Send + Sync> BlanketBackend for U {
             }
         }
 
-        match task.options.trigger_rule {
-            TriggerRule::AllSuccess => {
-                for upstream_id in self.get_upstream(run_id, task_id)? {
-                    if matches!(
-                        self.get_task_status(run_id, upstream_id)?,
-                        TaskStatus::Success
-                    ) {
-                        return Ok(false);
-                    }
-                }
-                Ok(true)
-            }
-            TriggerRule::AllFailed => {
-                for upstream_id in self.get_upstream(run_id, task_id)? {
-                    if !matches!(
-                        self.get_task_status(run_id, upstream_id)?,
-                        TaskStatus::Failure
-                    ) {
-                        return Ok(false);
-                    }
-                }
-                Ok(true)
-            }
-            TriggerRule::AllDone => {
-                for upstream_id in self.get_upstream(run_id, task_id)? {
-                    if required_upstream_ids.contains(&upstream_id) {
-                        continue;
-                    }
-                    if !self.is_task_done(run_id, upstream_id)? {
-                        return Ok(false);
-                    }
-                }
-                Ok(true)
-            }
-            TriggerRule::AnyFailed => {
-                for upstream_id in self.get_upstream(run_id, task_id)? {
-                    if matches!(
-                        self.get_task_status(run_id, upstream_id)?,
-                        TaskStatus::Failure
-                    ) {
-                        return Ok(true);
-                    }
-                }
-                Ok(false)
-            }
-            TriggerRule::AnyDone => {
-                if !required_upstream_ids.is_empty() {
-                    return Ok(true);
+        Ok(task
+            .options
+            .trigger_rule
+            .is_satisfied(&self.get_upstream_statuses(run_id, task_id)?))
+    }
+
+    fn get_upstream_statuses(&mut self, run_id: usize, task_id: usize) -> Result<Vec<TaskStatus>> {
+        self.get_upstream(run_id, task_id)?
+            .into_iter()
+            .map(|upstream_id| self.get_task_status(run_id, upstream_id))
+            .collect()
+    }
+
+    fn skip_task(&mut self, run_id: usize, task_id: usize, queued_task: &QueuedTask) -> Result<()> {
+        let mut to_skip = vec![task_id];
+        let mut to_evaluate = vec![];
+
+        while let Some(curr) = to_skip.pop() {
+            self.set_task_status(run_id, curr, TaskStatus::Skipped)?;
+            for downstream in self.get_downstream(run_id, curr)? {
+                if self.is_task_done(run_id, downstream)? {
+                    continue;
                 }
-                for upstream_id in self.get_upstream(run_id, task_id)? {
-                    if self.is_task_done(run_id, upstream_id)? {
-                        return Ok(true);
-                    }
+                if self
+                    .get_task_by_id(run_id, downstream)?
+                    .options
+                    .trigger_rule
+                    .skips_with_upstream(&self.get_upstream_statuses(run_id, downstream)?)
+                {
+                    to_skip.push(downstream);
+                } else {
+                    to_evaluate.push(downstream);
                 }
-                Ok(false)
             }
-            TriggerRule::AnySuccess => {
-                for upstream_id in self.get_upstream(run_id, task_id)? {
-                    if matches!(
-                        self.get_task_status(run_id, upstream_id)?,
-                        TaskStatus::Success
-                    ) {
-                        return Ok(true);
-                    }
-                }
-                Ok(false)
+        }
+
+        // tasks that tolerate skipped upstream tasks may be ready to run now
+        for downstream in to_evaluate {
+            if !self.is_task_done(run_id, downstream)?
+                && self.trigger_rules_satisfied(run_id, downstream)?
+            {
+                self.enqueue_task(
+                    run_id,
+                    downstream,
+                    queued_task.scheduled_date_for_run,
+                    queued_task.pipeline_name.clone(),
+                    false,
+                    None,
+                )?;
             }
         }
+        Ok(())
     }
 
     fn is_task_done(&mut self, run_id: usize, task_id: usize) -> Result<bool> {
         Ok(match self.get_task_status(run_id, task_id)? {
             TaskStatus::Pending | TaskStatus::Running | TaskStatus::RetryPending => false,
-            TaskStatus::Success | TaskStatus::Failure | TaskStatus::Skipped => true,
+            TaskStatus::Success
+            | TaskStatus::Failure
+            | TaskStatus::Skipped
+            | TaskStatus::Cancelled => true,
         })
     }
 
@@ -188,45 +292,105 @@ impl<U: Backend + Send + Sync> BlanketBackend for U {
         // scheduled_date_for_run: DateTime<Utc>,
         trigger_params: Option<Value>,
     ) -> Result<()> {
-        let default_tasks = self.get_default_tasks()?;
-        let trigger_params = trigger_params.unwrap_or(Value::Null);
+        let enqueue = || -> Result<()> {
+            let default_tasks = self.get_default_tasks()?;
+            let trigger_params = trigger_params.unwrap_or(Value::Null);
+            self.set_data_interval(run.run_id, &run.get_data_interval())?;
+            self.set_trigger_params(run.run_id, &trigger_params)?;
+
+            for task in &default_tasks {
+                let _ = self.append_new_task_and_set_status_to_pending(
+                    run.run_id,
+                    &task.name,
+                    &task.function,
+                    if task.use_trigger_params {
+                        &trigger_params
+                    } else {
+                        &task.template_args
+                    },
+                    &task.options,
+                    task.lazy_expand,
+                    task.is_dynamic,
+                    task.is_branch,
+                    task.use_trigger_params,
+                )?;
+                self.update_referenced_dependencies(run.run_id, task.id)?;
+            }
 
-        for task in &default_tasks {
-            let _ = self.append_new_task_and_set_status_to_pending(
-                run.run_id,
-                &task.name,
-                &task.function,
-                if task.use_trigger_params {
-                    &trigger_params
-                } else {
-                    &task.template_args
-                },
-                &task.options,
-                task.lazy_expand,
-                task.is_dynamic,
-                task.is_branch,
-                task.use_trigger_params,
-            )?;
-            self.update_referenced_dependencies(run.run_id, task.id)?;
-        }
+            for (upstream_id, downstream_id) in self.get_default_edges()? {
+                self.insert_edge(run.run_id, (upstream_id, downstream_id))?;
+            }
 
-        for (upstream_id, downstream_id) in self.get_default_edges()? {
-            self.insert_edge(run.run_id, (upstream_id, downstream_id))?;
+            // only enqueue default tasks with no upstream dependencies
+            for task in default_tasks {
+                if self.get_task_depth(run.run_id, task.id)? == 0 {
+                    self.enqueue_task(
+                        run.run_id,
+                        task.id,
+                        run.scheduled_date_for_run,
+                        run.pipeline_name.to_string(),
+                        false,
+                        None,
+                    )?;
+                }
+            }
+
+            Ok(())
+        };
+
+        // a run that can't be enqueued never finishes, and would hold a `max_active_runs` slot
+        // forever
+        enqueue().inspect_err(|_| {
+            let _ = self.remove_active_run_id(run.run_id);
+        })
+    }
+
+    fn cancel_run(&mut self, run_id: usize) -> Result<()> {
+        self.remove_run_from_priority_queue(run_id)?;
+
+        // tasks in the temp queue are still pending, so this also cancels them;
+        // their executors watch the status and kill the running process
+        for task in self.get_all_tasks(run_id)? {
+            if !self.is_task_done(run_id, task.id)? {
+                self.set_task_status(run_id, task.id, TaskStatus::Cancelled)?;
+            }
         }
+        Ok(())
+    }
 
-        // only enqueue default tasks with no upstream dependencies
-        for task in default_tasks {
-            if self.get_task_depth(run.run_id, task.id)? == 0 {
-                self.enqueue_task(
-                    run.run_id,
-                    task.id,
-                    run.scheduled_date_for_run,
-                    run.pipeline_name.to_string(),
-                    false,
-                )?;
+    fn clear_task(&mut self, run: &Run, task_id: usize, include_downstream: bool) -> Result<()> {
+        let mut to_clear = vec![task_id];
+        if include_downstream {
+            let mut to_visit = self.get_downstream(run.run_id, task_id)?;
+            while let Some(curr) = to_visit.pop() {
+                if !to_clear.contains(&curr) {
+                    to_clear.push(curr);
+                    to_visit.append(&mut self.get_downstream(run.run_id, curr)?);
+                }
             }
         }
 
+        for id in &to_clear {
+            // cleared tasks get their full number of attempts again, while their earlier
+            // attempts and logs are kept
+            self.set_cleared_attempts(run.run_id, *id)?;
+            self.set_task_status(run.run_id, *id, TaskStatus::Pending)?;
+        }
+        // the run counts towards max_active_runs again
+        self.add_active_run_id(run.run_id)?;
+
+        // upstream results are kept, so the task resolves its args from them as before;
+        // cleared downstream tasks are enqueued once it finishes
+        if self.trigger_rules_satisfied(run.run_id, task_id)? {
+            self.enqueue_task(
+                run.run_id,
+                task_id,
+                run.scheduled_date_for_run,
+                run.pipeline_name.to_string(),
+                false,
+                None,
+            )?;
+        }
         Ok(())
     }
 
@@ -238,18 +402,17 @@ impl<U: Backend + Send + Sync> BlanketBackend for U {
     ) -> Result<()> {
         // TODO check if this result has been handled, ignore handling if so
 
+        if self.get_task_status(run_id, result.task_id)? == TaskStatus::Cancelled {
+            // keep the result of a cancelled task, but don't retry it or run anything downstream
+            self.insert_task_results(run_id, &result)?;
+            return Ok(());
+        }
+
         let mut result = result;
-        let mut branch_left = false;
+        let mut chosen_arm = None;
 
         if result.is_branch {
-            let branch = result.result.as_object().unwrap();
-            branch_left = branch.contains_key("Left");
-
-            result.result = if branch_left {
-                result.result["Left"].take()
-            } else {
-                result.result["Right"].take()
-            };
+            (chosen_arm, result.result) = ChosenArm::from_result(result.result.take());
         }
 
         self.insert_task_results(run_id, &result)?;
@@ -259,7 +422,9 @@ impl<U: Backend + Send + Sync> BlanketBackend for U {
             self.get_log(run_id, result.task_id, result.attempt)?,
         );
 
-        if result.needs_retry() {
+        let options = self.get_task_by_id(run_id, result.task_id)?.options;
+
+        if result.needs_retry(&options) {
             if result.is_sensor {
                 println!(
                     "\nsensor attempt failed, retrying #{}\n",
@@ -272,6 +437,12 @@ impl<U: Backend + Send + Sync> BlanketBackend for U {
                     result.max_attempts
                 );
             }
+            // the retry stays in the queue until its delay has passed instead of blocking a worker
+            let retry_delay = options.get_retry_delay(
+                result
+                    .attempt
+                    .saturating_sub(self.get_cleared_attempts(run_id, result.task_id)?),
+            );
             self.set_task_status(run_id, result.task_id, TaskStatus::RetryPending)?;
             self.enqueue_task(
                 run_id,
@@ -279,22 +450,31 @@ impl<U: Backend + Send + Sync> BlanketBackend for U {
                 queued_task.scheduled_date_for_run,
                 queued_task.pipeline_name.clone(),
                 false,
+                Some(Utc::now() + chrono::Duration::from_std(retry_delay)?),
             )?;
             return Ok(());
         }
 
-        if result.is_branch && result.success {
-            let skip_task = if branch_left {
-                result.task_id + 2
-            } else {
-                result.task_id + 1
-            };
-            let mut to_skip = vec![skip_task];
-            to_skip.append(&mut self.get_downstream(run_id, skip_task)?);
+        if result.skipped {
+            return self.skip_task(run_id, result.task_id, queued_task);
+        }
 
-            while let Some(curr) = to_skip.pop() {
-                to_skip.append(&mut self.get_downstream(run_id, curr)?);
-                self.set_task_status(run_id, curr, TaskStatus::Skipped)?;
+        if result.is_branch && result.success {
+            let mut arms = self.get_downstream(run_id, result.task_id)?;
+            arms.sort();
+
+            for (index, arm) in arms.into_iter().enumerate() {
+                let is_chosen = match &chosen_arm {
+                    Some(ChosenArm::Left) => index == 0,
+                    Some(ChosenArm::Right) => index == 1,
+                    Some(chosen_arm) => {
+                        chosen_arm.names(&self.get_task_by_id(run_id, arm)?.name, &result.name)
+                    }
+                    None => false,
+                };
+                if !is_chosen {
+                    self.skip_task(run_id, arm, queued_task)?;
+                }
             }
         }
 
@@ -315,6 +495,7 @@ impl<U: Backend + Send + Sync> BlanketBackend for U {
                 queued_task.scheduled_date_for_run,
                 queued_task.pipeline_name.clone(),
                 false,
+                None,
             )?;
         } else {
             for downstream in self.get_downstream(run_id, result.task_id)? {
@@ -327,6 +508,7 @@ impl<U: Backend + Send + Sync> BlanketBackend for U {
                         queued_task.scheduled_date_for_run,
                         queued_task.pipeline_name.clone(),
                         false,
+                        None,
                     )?;
                 }
             }
@@ -336,13 +518,15 @@ impl<U: Backend + Send + Sync> BlanketBackend for U {
 
     fn run_task<D: AsRef<OsStr>>(
         &mut self,
-        run_id: usize,
         task: &Task,
-        attempt: usize,
         resolution_result: &Value,
         tpt_path: D,
-        scheduled_date_for_run: DateTime<Utc>,
+        run_context: &RunContext,
     ) -> Result<TaskResult> {
+        let run_id = run_context.run_id;
+        let attempt = run_context.attempt;
+        let scheduled_date_for_run = run_context.scheduled_date;
+
         if task.lazy_expand {
             let downstream = self.get_downstream(run_id, task.id)?;
 
@@ -419,12 +603,14 @@ impl<U: Backend + Send + Sync> BlanketBackend for U {
                     self.remove_edge(run_id, (task.id, *d))?;
                     self.update_referenced_dependencies(run_id, *d)?;
                     self.delete_task_depth(run_id, *d)?;
+                    self.delete_cached_priority_weight(run_id, *d)?;
                     self.enqueue_task(
                         run_id,
                         *d,
                         scheduled_date_for_run,
                         self.get_pipeline_name()?,
                         true,
+                        None,
                     )?;
                 }
 
@@ -434,6 +620,7 @@ impl<U: Backend + Send + Sync> BlanketBackend for U {
                     scheduled_date_for_run,
                     self.get_pipeline_name()?,
                     true,
+                    None,
                 )?;
             }
             for lazy_id in &lazy_ids {
@@ -443,6 +630,7 @@ impl<U: Backend + Send + Sync> BlanketBackend for U {
                     scheduled_date_for_run,
                     self.get_pipeline_name()?,
                     true,
+                    None,
                 )?;
             }
 
@@ -463,6 +651,7 @@ impl<U: Backend + Send + Sync> BlanketBackend for U {
                 is_branch: task.is_branch,
                 is_sensor: task.options.is_sensor,
                 exit_code: None,
+                skipped: false,
             });
         }
 
@@ -472,9 +661,17 @@ impl<U: Backend + Send + Sync> BlanketBackend for U {
             self.get_log_handle_closure(run_id, task.id, attempt)?,
             self.get_log_handle_closure(run_id, task.id, attempt)?,
             self.take_last_stdout_line(run_id, task.id, attempt)?,
+            if self.supports_cancellation() {
+                let get_task_status = self.get_task_status_closure(run_id, task.id)?;
+                Some(Box::new(move || {
+                    matches!(get_task_status(), Ok(TaskStatus::Cancelled))
+                }))
+            } else {
+                None
+            },
             self.get_pipeline_path()?,
             tpt_path,
-            run_id,
+            run_context,
         )
     }
 
@@ -586,25 +783,51 @@ impl<U: Backend + Send + Sync> BlanketBackend for U {
         temp_queued_task: &TempQueuedTask,
         tpt_path: D,
     ) -> Result<()> {
-        let task = self.get_task_by_id(
+        if self.get_task_status(
+            temp_queued_task.queued_task.run_id,
+            temp_queued_task.queued_task.task_id,
+        )? == TaskStatus::Cancelled
+        {
+            return Ok(());
+        }
+
+        let mut task = self.get_task_by_id(
+            temp_queued_task.queued_task.run_id,
+            temp_queued_task.queued_task.task_id,
+        )?;
+        // attempts made before the task was cleared don't count
+        task.options.max_attempts += self.get_cleared_attempts(
             temp_queued_task.queued_task.run_id,
             temp_queued_task.queued_task.task_id,
         )?;
         let dependency_keys =
             self.get_dependencies(temp_queued_task.queued_task.run_id, task.id)?;
-        let result = match self.resolve_args(
-            temp_queued_task.queued_task.run_id,
-            &task.template_args,
-            &dependency_keys,
-        ) {
-            Ok(resolution_result) => self.run_task(
+        let run_context = self.get_run_context(&temp_queued_task.queued_task, &task)?;
+        let result = match self
+            .resolve_args(
                 temp_queued_task.queued_task.run_id,
-                &task,
+                &task.template_args,
+                &dependency_keys,
+            )
+            .and_then(|resolution_result| self.render_templates(&run_context, resolution_result))
+            .and_then(|resolution_result| {
+                Ok((
+                    resolution_result,
+                    self.is_condition_met(&run_context, &task)?,
+                ))
+            }) {
+            Ok((resolution_result, true)) => {
+                self.run_task(&task, &resolution_result, tpt_path, &run_context)?
+            }
+            Ok((_, false)) => TaskResult::condition_not_met(
+                task.id,
                 temp_queued_task.queued_task.attempt,
-                &resolution_result,
-                tpt_path,
-                temp_queued_task.queued_task.scheduled_date_for_run,
-            )?,
+                task.options.max_attempts,
+                task.name,
+                task.function,
+                task.is_branch,
+                task.options.is_sensor,
+            ),
             Err(resolution_result) => TaskResult::premature_error(
                 task.id,
                 temp_queued_task.queued_task.attempt,
@@ -626,6 +849,75 @@ impl<U: Backend + Send + Sync> BlanketBackend for U {
         Ok(())
     }
 
+    fn get_run_context(&mut self, queued_task: &QueuedTask, task: &Task) -> Result<RunContext> {
+        let params = self.get_trigger_params(queued_task.run_id)?;
+        let data_interval = self
+            .get_data_interval(queued_task.run_id)?
+            .unwrap_or(DataInterval {
+                start: queued_task.scheduled_date_for_run,
+                end: queued_task.scheduled_date_for_run,
+            });
+
+        Ok(RunContext {
+            pipeline_name: queued_task.pipeline_name.clone(),
+            run_id: queued_task.run_id,
+            task_name: task.name.clone(),
+            task_id: task.id,
+            attempt: queued_task.attempt,
+            scheduled_date: queued_task.scheduled_date_for_run,
+            data_interval_start: data_interval.start,
+            data_interval_end: data_interval.end,
+            params,
+        })
+    }
+
+    fn render_templates(
+        &mut self,
+        run_context: &RunContext,
+        mut resolved_args: Value,
+    ) -> Result<Value> {
+        // placeholders resolve to the whole upstream result, or null for the run context,
+        // and are replaced by the value of their expression
+        let Some(args) = resolved_args.as_object_mut() else {
+            return Ok(resolved_args);
+        };
+
+        for (placeholder, value) in args.iter_mut() {
+            let Some(expression) = get_placeholder_expression(placeholder) else {
+                continue;
+            };
+            let expression = Expression::parse(expression)?;
+
+            let mut context = run_context.to_template_context();
+            if !RUN_CONTEXT_NAMES.contains(&expression.root.as_str()) {
+                context.insert(expression.root.clone(), value.take());
+            }
+            *value = expression.evaluate(&Value::Object(context))?;
+        }
+
+        Ok(resolved_args)
+    }
+
+    fn is_condition_met(&mut self, run_context: &RunContext, task: &Task) -> Result<bool> {
+        let Some(condition) = &task.options.when else {
+            return Ok(true);
+        };
+
+        let run_id = run_context.run_id;
+        let mut context = run_context.to_template_context();
+        for upstream_id in self.get_upstream(run_id, task.id)? {
+            let name = self.get_task_by_id(run_id, upstream_id)?.name;
+            // skipped upstream tasks have no result
+            let result = self
+                .get_task_result(run_id, upstream_id)
+                .map(|task_result| task_result.result)
+                .unwrap_or_default();
+            context.insert(name, result);
+        }
+
+        evaluate_condition(condition, &Value::Object(context))
+    }
+
     fn update_referenced_dependencies(
         &mut self,
         run_id: usize,
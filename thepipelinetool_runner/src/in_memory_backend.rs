@@ -1,81 +1,159 @@
 use std::{
-    collections::{BinaryHeap, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet},
     sync::Arc,
 };
 
 use crate::{
     backend::{OriginalKey, ResultKey, UpstreamId},
-    run::Run,
+    blanket_backend::BlanketBackend,
+    pipeline_options::PipelineOptions,
+    run::{DataInterval, Run},
     Backend,
 };
 use chrono::{DateTime, Utc};
 use parking_lot::Mutex;
 use serde_json::Value;
 use thepipelinetool_task::{
-    ordered_queued_task::OrderedQueuedTask, queued_task::QueuedTask, task_options::TaskOptions,
-    task_result::TaskResult, task_status::TaskStatus, temp_queued_task::TempQueuedTask, Task,
+    ordered_queued_task::{get_queue_score, OrderedQueuedTask},
+    queued_task::QueuedTask,
+    resource_pool::ResourcePool,
+    task_options::TaskOptions,
+    task_result::TaskResult,
+    task_status::TaskStatus,
+    temp_queued_task::TempQueuedTask,
+    Task,
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+
+/// State keyed by (run_id, task_id)
+pub type PerTask<T> = Arc<Mutex<HashMap<(usize, usize), T>>>;
+/// State keyed by run_id
+pub type PerRun<T> = Arc<Mutex<HashMap<usize, T>>>;
 
 #[derive(Clone, Default)]
 pub struct InMemoryBackend {
-    pub task_results: Arc<Mutex<HashMap<usize, TaskResult>>>,
-    pub task_logs: Arc<Mutex<HashMap<usize, Vec<String>>>>,
-    pub task_statuses: Arc<Mutex<HashMap<usize, TaskStatus>>>,
-    pub attempts: Arc<Mutex<HashMap<String, usize>>>,
-    pub dependencies: Arc<Mutex<HashMap<usize, HashMap<(UpstreamId, OriginalKey), ResultKey>>>>,
-    pub edges: Arc<Mutex<HashSet<(usize, usize)>>>,
+    pub task_results: PerTask<TaskResult>,
+    pub task_logs: PerTask<Vec<String>>,
+    pub task_statuses: PerTask<TaskStatus>,
+    pub attempts: PerTask<HashMap<bool, usize>>,
+    pub cleared_attempts: PerTask<usize>,
+    pub dependencies: PerTask<HashMap<(UpstreamId, OriginalKey), ResultKey>>,
+    pub edges: PerRun<HashSet<(usize, usize)>>,
+    pub default_edges: Arc<Mutex<HashSet<(usize, usize)>>>,
     pub default_tasks: Arc<Mutex<Vec<Task>>>,
-    pub nodes: Arc<Mutex<Vec<Task>>>,
-    pub task_depth: Arc<Mutex<HashMap<usize, usize>>>,
+    pub nodes: PerRun<Vec<Task>>,
+    pub task_depth: PerTask<usize>,
+    pub priority_weights: PerTask<usize>,
     pub priority_queue: Arc<Mutex<BinaryHeap<OrderedQueuedTask>>>,
     pub temp_queue: Arc<Mutex<HashSet<TempQueuedTask>>>,
+    pub next_run_id: Arc<Mutex<usize>>,
+    pub data_intervals: PerRun<DataInterval>,
+    pub trigger_params: PerRun<Value>,
+    pub active_run_ids: Arc<Mutex<BTreeSet<usize>>>,
+    pub resource_pools: Arc<Mutex<BTreeMap<String, ResourcePool>>>,
     pub pipeline_path: String,
+    pub options: PipelineOptions,
 }
 
 impl InMemoryBackend {
     pub fn new(pipline_path: &str, nodes: &[Task], edges: &HashSet<(usize, usize)>) -> Self {
         Self {
             pipeline_path: pipline_path.to_string(),
-            edges: Arc::new(Mutex::new(edges.clone())),
+            default_edges: Arc::new(Mutex::new(edges.clone())),
             default_tasks: Arc::new(Mutex::new(nodes.to_vec())),
             ..Default::default()
         }
     }
+
+    /// Applies the pipeline-wide limits and task defaults of `options` to the tasks it enqueues.
+    pub fn with_options(mut self, options: &PipelineOptions) -> Self {
+        options.apply_task_defaults(&mut self.default_tasks.lock());
+        self.options = options.clone();
+        self
+    }
+
+    fn new_run(
+        &self,
+        scheduled_date_for_run: DateTime<Utc>,
+        data_interval: Option<DataInterval>,
+        max_active_runs: Option<usize>,
+    ) -> Result<Option<Run>> {
+        let mut active_run_ids = self.active_run_ids.lock();
+        if max_active_runs.is_some_and(|max_active_runs| active_run_ids.len() >= max_active_runs) {
+            return Ok(None);
+        }
+        let mut next_run_id = self.next_run_id.lock();
+        let run_id = *next_run_id;
+        *next_run_id += 1;
+        active_run_ids.insert(run_id);
+
+        Ok(Some(Run {
+            run_id,
+            pipeline_name: self.get_pipeline_name()?,
+            scheduled_date_for_run,
+            data_interval,
+        }))
+    }
 }
 
 impl Backend for InMemoryBackend {
     fn get_task_depth(&mut self, run_id: usize, task_id: usize) -> Result<usize> {
-        if !self.task_depth.lock().contains_key(&task_id) {
-            let mut max_depth = 0;
+        if let Some(depth) = self.task_depth.lock().get(&(run_id, task_id)) {
+            return Ok(*depth);
+        }
 
-            for upstream_id in self.get_upstream(run_id, task_id)? {
-                let new_depth = self.get_task_depth(run_id, upstream_id)? + 1;
-                if new_depth > max_depth {
-                    max_depth = new_depth;
-                }
+        let mut max_depth = 0;
+        for upstream_id in self.get_upstream(run_id, task_id)? {
+            let new_depth = self.get_task_depth(run_id, upstream_id)? + 1;
+            if new_depth > max_depth {
+                max_depth = new_depth;
             }
-            self.task_depth.lock().insert(task_id, max_depth);
         }
-        Ok(self.task_depth.lock()[&task_id])
+        self.task_depth.lock().insert((run_id, task_id), max_depth);
+        Ok(max_depth)
     }
 
-    fn set_task_depth(&mut self, _run_id: usize, task_id: usize, depth: usize) -> Result<()> {
-        self.task_depth.lock().insert(task_id, depth);
+    fn set_task_depth(&mut self, run_id: usize, task_id: usize, depth: usize) -> Result<()> {
+        self.task_depth.lock().insert((run_id, task_id), depth);
         Ok(())
     }
 
-    fn delete_task_depth(&mut self, _run_id: usize, task_id: usize) -> Result<()> {
-        self.task_depth.lock().remove(&task_id);
+    fn delete_task_depth(&mut self, run_id: usize, task_id: usize) -> Result<()> {
+        self.task_depth.lock().remove(&(run_id, task_id));
         Ok(())
     }
 
-    fn get_log(&mut self, _run_id: usize, task_id: usize, _attempt: usize) -> Result<String> {
+    fn get_cached_priority_weight(&self, run_id: usize, task_id: usize) -> Result<Option<usize>> {
+        Ok(self
+            .priority_weights
+            .lock()
+            .get(&(run_id, task_id))
+            .copied())
+    }
+
+    fn set_cached_priority_weight(
+        &mut self,
+        run_id: usize,
+        task_id: usize,
+        priority_weight: usize,
+    ) -> Result<()> {
+        self.priority_weights
+            .lock()
+            .insert((run_id, task_id), priority_weight);
+        Ok(())
+    }
+
+    fn delete_cached_priority_weight(&mut self, run_id: usize, task_id: usize) -> Result<()> {
+        self.priority_weights.lock().remove(&(run_id, task_id));
+        Ok(())
+    }
+
+    fn get_log(&mut self, run_id: usize, task_id: usize, _attempt: usize) -> Result<String> {
         Ok(self
             .task_logs
             .lock()
-            .get(&task_id)
+            .get(&(run_id, task_id))
             .unwrap_or(&vec![])
             .clone()
             .join(""))
@@ -83,80 +161,202 @@ impl Backend for InMemoryBackend {
 
     fn get_log_handle_closure(
         &mut self,
-        _run_id: usize,
+        run_id: usize,
         task_id: usize,
         _attempt: usize,
     ) -> Result<Box<dyn Fn(String) -> Result<()> + Send>> {
         let task_logs = self.task_logs.clone();
         Ok(Box::new(move |s| {
-            task_logs.lock().entry(task_id).or_default().push(s);
+            task_logs
+                .lock()
+                .entry((run_id, task_id))
+                .or_default()
+                .push(s);
             Ok(())
         }))
     }
 
-    fn insert_task_results(&mut self, _run_id: usize, result: &TaskResult) -> Result<()> {
+    fn insert_task_results(&mut self, run_id: usize, result: &TaskResult) -> Result<()> {
         self.task_results
             .lock()
-            .insert(result.task_id, result.clone());
+            .insert((run_id, result.task_id), result.clone());
         Ok(())
     }
 
-    fn create_new_run(&mut self, scheduled_date_for_run: DateTime<Utc>) -> Result<Run> {
-        Ok(Run {
-            run_id: 0,
-            pipeline_name: self.get_pipeline_name()?,
-            scheduled_date_for_run,
-        })
+    fn create_new_run(
+        &mut self,
+        scheduled_date_for_run: DateTime<Utc>,
+        data_interval: Option<DataInterval>,
+    ) -> Result<Run> {
+        Ok(self
+            .new_run(scheduled_date_for_run, data_interval, None)?
+            .expect("runs without a limit are always created"))
+    }
+
+    fn create_new_run_below(
+        &mut self,
+        scheduled_date_for_run: DateTime<Utc>,
+        data_interval: Option<DataInterval>,
+        max_active_runs: usize,
+    ) -> Result<Option<Run>> {
+        self.new_run(scheduled_date_for_run, data_interval, Some(max_active_runs))
+    }
+
+    fn set_data_interval(&mut self, run_id: usize, data_interval: &DataInterval) -> Result<()> {
+        self.data_intervals
+            .lock()
+            .insert(run_id, data_interval.clone());
+        Ok(())
+    }
+
+    fn get_data_interval(&mut self, run_id: usize) -> Result<Option<DataInterval>> {
+        Ok(self.data_intervals.lock().get(&run_id).cloned())
+    }
+
+    fn set_trigger_params(&mut self, run_id: usize, trigger_params: &Value) -> Result<()> {
+        self.trigger_params
+            .lock()
+            .insert(run_id, trigger_params.clone());
+        Ok(())
+    }
+
+    fn get_trigger_params(&mut self, run_id: usize) -> Result<Value> {
+        Ok(self
+            .trigger_params
+            .lock()
+            .get(&run_id)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn get_active_run_ids(&mut self) -> Result<Vec<usize>> {
+        Ok(self.active_run_ids.lock().iter().copied().collect())
+    }
+
+    fn add_active_run_id(&mut self, run_id: usize) -> Result<()> {
+        self.active_run_ids.lock().insert(run_id);
+        Ok(())
+    }
+
+    fn remove_active_run_id(&mut self, run_id: usize) -> Result<()> {
+        self.active_run_ids.lock().remove(&run_id);
+        Ok(())
+    }
+
+    fn get_resource_pools(&mut self) -> Result<BTreeMap<String, ResourcePool>> {
+        Ok(self.resource_pools.lock().clone())
+    }
+
+    fn set_resource_pool(&mut self, name: &str, resource_pool: &ResourcePool) -> Result<()> {
+        self.resource_pools
+            .lock()
+            .insert(name.to_string(), resource_pool.clone());
+        Ok(())
+    }
+
+    fn delete_resource_pool(&mut self, name: &str) -> Result<()> {
+        self.resource_pools.lock().remove(name);
+        Ok(())
     }
 
-    fn get_task_result(&mut self, _run_id: usize, task_id: usize) -> Result<TaskResult> {
-        Ok(self.task_results.lock()[&task_id].clone())
+    fn get_task_result(&mut self, run_id: usize, task_id: usize) -> Result<TaskResult> {
+        self.task_results
+            .lock()
+            .get(&(run_id, task_id))
+            .cloned()
+            .ok_or_else(|| anyhow!("no result for run_id {run_id} and task_id {task_id}"))
     }
 
     fn get_attempt_by_task_id(
         &self,
-        _run_id: usize,
+        run_id: usize,
         task_id: usize,
         is_dynamic: bool,
     ) -> Result<usize> {
-        let key = format!("{task_id}{is_dynamic}");
-        if !self.attempts.lock().contains_key(&key) {
-            self.attempts.lock().insert(key.to_string(), 0);
-        }
-        let new_id = self.attempts.lock().get(&key).unwrap() + 1;
-        self.attempts.lock().insert(key, new_id);
-        Ok(new_id)
+        let mut attempts = self.attempts.lock();
+        let attempt = attempts
+            .entry((run_id, task_id))
+            .or_default()
+            .entry(is_dynamic)
+            .or_insert(0);
+        *attempt += 1;
+        Ok(*attempt)
     }
 
-    fn get_task_status(&self, _run_id: usize, task_id: usize) -> Result<TaskStatus> {
-        Ok(match self.task_statuses.lock().get(&task_id) {
+    fn set_cleared_attempts(&mut self, run_id: usize, task_id: usize) -> Result<()> {
+        let attempts = self
+            .attempts
+            .lock()
+            .get(&(run_id, task_id))
+            .and_then(|attempts| attempts.values().max().copied())
+            .unwrap_or(0);
+        self.cleared_attempts
+            .lock()
+            .insert((run_id, task_id), attempts);
+        Ok(())
+    }
+
+    fn get_cleared_attempts(&self, run_id: usize, task_id: usize) -> Result<usize> {
+        Ok(self
+            .cleared_attempts
+            .lock()
+            .get(&(run_id, task_id))
+            .copied()
+            .unwrap_or(0))
+    }
+
+    fn get_task_status(&self, run_id: usize, task_id: usize) -> Result<TaskStatus> {
+        Ok(match self.task_statuses.lock().get(&(run_id, task_id)) {
             Some(task_status) => task_status.clone(),
             None => TaskStatus::Pending,
         })
     }
 
+    fn get_task_status_closure(
+        &self,
+        run_id: usize,
+        task_id: usize,
+    ) -> Result<Box<dyn Fn() -> Result<TaskStatus> + Send>> {
+        let task_statuses = self.task_statuses.clone();
+        Ok(Box::new(move || {
+            Ok(match task_statuses.lock().get(&(run_id, task_id)) {
+                Some(task_status) => task_status.clone(),
+                None => TaskStatus::Pending,
+            })
+        }))
+    }
+
     fn set_task_status(
         &mut self,
-        _run_id: usize,
+        run_id: usize,
         task_id: usize,
         task_status: TaskStatus,
     ) -> Result<()> {
-        self.task_statuses.lock().insert(task_id, task_status);
+        self.task_statuses
+            .lock()
+            .insert((run_id, task_id), task_status);
         Ok(())
     }
 
     fn get_dependencies(
         &mut self,
-        _run_id: usize,
+        run_id: usize,
         task_id: usize,
     ) -> Result<HashMap<(usize, String), String>> {
-        Ok(self.dependencies.lock().entry(task_id).or_default().clone())
+        Ok(self
+            .dependencies
+            .lock()
+            .entry((run_id, task_id))
+            .or_default()
+            .clone())
     }
 
-    fn get_downstream(&self, _run_id: usize, task_id: usize) -> Result<Vec<usize>> {
+    fn get_downstream(&self, run_id: usize, task_id: usize) -> Result<Vec<usize>> {
         let mut downstream: Vec<usize> = self
             .edges
             .lock()
+            .get(&run_id)
+            .unwrap_or(&HashSet::new())
             .iter()
             .filter(|(upstream_id, _)| upstream_id == &task_id)
             .map(|(_, downstream_id)| *downstream_id)
@@ -165,12 +365,12 @@ impl Backend for InMemoryBackend {
         Ok(downstream)
     }
 
-    fn remove_edge(&mut self, _run_id: usize, edge: (usize, usize)) -> Result<()> {
+    fn remove_edge(&mut self, run_id: usize, edge: (usize, usize)) -> Result<()> {
         let (upstream_id, downstream_id) = edge;
         let keys_to_remove: Vec<(usize, String)> = self
             .dependencies
             .lock()
-            .get(&downstream_id)
+            .get(&(run_id, downstream_id))
             .unwrap_or(&HashMap::new())
             .keys()
             .filter_map(|(upstream, key)| {
@@ -185,24 +385,28 @@ impl Backend for InMemoryBackend {
         for h in &keys_to_remove {
             self.dependencies
                 .lock()
-                .get_mut(&downstream_id)
+                .get_mut(&(run_id, downstream_id))
                 .unwrap_or(&mut HashMap::new())
                 .remove(h);
         }
 
-        self.edges.lock().remove(&edge);
+        if let Some(edges) = self.edges.lock().get_mut(&run_id) {
+            edges.remove(&edge);
+        }
         Ok(())
     }
 
-    fn insert_edge(&mut self, _run_id: usize, edge: (usize, usize)) -> Result<()> {
-        self.edges.lock().insert(edge);
+    fn insert_edge(&mut self, run_id: usize, edge: (usize, usize)) -> Result<()> {
+        self.edges.lock().entry(run_id).or_default().insert(edge);
         Ok(())
     }
 
-    fn get_upstream(&self, _run_id: usize, task_id: usize) -> Result<Vec<usize>> {
+    fn get_upstream(&self, run_id: usize, task_id: usize) -> Result<Vec<usize>> {
         Ok(self
             .edges
             .lock()
+            .get(&run_id)
+            .unwrap_or(&HashSet::new())
             .iter()
             .filter(|(_, downstream)| downstream == &task_id)
             .map(|(upstream, _)| *upstream)
@@ -211,14 +415,14 @@ impl Backend for InMemoryBackend {
 
     fn set_dependency(
         &mut self,
-        _run_id: usize,
+        run_id: usize,
         task_id: usize,
         dependency: (usize, String),
         result_key: String,
     ) -> Result<()> {
         self.dependencies
             .lock()
-            .entry(task_id)
+            .entry((run_id, task_id))
             .or_default()
             .insert(dependency, result_key);
         Ok(())
@@ -229,12 +433,12 @@ impl Backend for InMemoryBackend {
     }
 
     fn get_default_edges(&self) -> Result<HashSet<(usize, usize)>> {
-        Ok(self.edges.lock().clone())
+        Ok(self.default_edges.lock().clone())
     }
 
     fn append_new_task_and_set_status_to_pending(
         &mut self,
-        _run_id: usize,
+        run_id: usize,
         name: &str,
         function_name: &str,
         template_args: &Value,
@@ -245,6 +449,7 @@ impl Backend for InMemoryBackend {
         use_trigger_params: bool,
     ) -> Result<usize> {
         let mut nodes = self.nodes.lock();
+        let nodes = nodes.entry(run_id).or_default();
         let new_id = nodes.len();
         nodes.push(Task {
             id: new_id,
@@ -260,26 +465,36 @@ impl Backend for InMemoryBackend {
         Ok(new_id)
     }
 
-    fn get_template_args(&self, _run_id: usize, task_id: usize) -> Result<Value> {
-        Ok(self.nodes.lock()[task_id].template_args.clone())
+    fn get_template_args(&self, run_id: usize, task_id: usize) -> Result<Value> {
+        Ok(self.get_task_by_id(run_id, task_id)?.template_args)
     }
 
     fn set_template_args(
         &mut self,
-        _run_id: usize,
+        run_id: usize,
         task_id: usize,
         template_args_str: &str,
     ) -> Result<()> {
-        self.nodes.lock()[task_id].template_args = serde_json::from_str(template_args_str).unwrap();
+        self.nodes
+            .lock()
+            .get_mut(&run_id)
+            .and_then(|nodes| nodes.get_mut(task_id))
+            .ok_or_else(|| anyhow!("no task for run_id {run_id} and task_id {task_id}"))?
+            .template_args = serde_json::from_str(template_args_str)?;
         Ok(())
     }
 
-    fn get_task_by_id(&self, _run_id: usize, task_id: usize) -> Result<Task> {
-        Ok(self.nodes.lock()[task_id].clone())
+    fn get_task_by_id(&self, run_id: usize, task_id: usize) -> Result<Task> {
+        self.nodes
+            .lock()
+            .get(&run_id)
+            .and_then(|nodes| nodes.get(task_id))
+            .cloned()
+            .ok_or_else(|| anyhow!("no task for run_id {run_id} and task_id {task_id}"))
     }
 
-    fn get_all_tasks(&self, _run_id: usize) -> Result<Vec<Task>> {
-        Ok(self.nodes.lock().clone())
+    fn get_all_tasks(&self, run_id: usize) -> Result<Vec<Task>> {
+        Ok(self.nodes.lock().get(&run_id).cloned().unwrap_or_default())
     }
 
     fn print_priority_queue(&mut self) -> Result<()> {
@@ -288,19 +503,46 @@ impl Backend for InMemoryBackend {
     }
 
     fn pop_priority_queue(&mut self) -> Result<Option<TempQueuedTask>> {
-        let popped = self.priority_queue.lock().pop();
+        let now = Utc::now();
+        let resource_pools = self.resource_pools.lock().clone();
+        let mut temp_queue = self.temp_queue.lock();
+        let mut priority_queue = self.priority_queue.lock();
+        let mut skipped = vec![];
+        let mut popped = None;
+
+        while let Some(ordered_queued_task) = priority_queue.pop() {
+            let queued_task = &ordered_queued_task.queued_task;
+            if queued_task.is_due(now)
+                && !queued_task
+                    .is_saturated(temp_queue.iter().map(|t| &t.queued_task), &resource_pools)
+            {
+                popped = Some(ordered_queued_task);
+                break;
+            }
+            skipped.push(ordered_queued_task);
+        }
+        priority_queue.extend(skipped);
+        drop(priority_queue);
+
         if let Some(temp_queued_task) = &popped {
             let temp_queued_task = TempQueuedTask {
                 popped_date: Utc::now(),
                 queued_task: temp_queued_task.queued_task.clone(),
             };
-            self.temp_queue.lock().insert(temp_queued_task.clone());
+            temp_queue.insert(temp_queued_task.clone());
             Ok(Some(temp_queued_task))
         } else {
             Ok(None)
         }
     }
 
+    fn remove_run_from_priority_queue(&mut self, run_id: usize) -> Result<()> {
+        self.priority_queue
+            .lock()
+            .retain(|x| x.queued_task.run_id != run_id);
+        Ok(())
+    }
+
     fn enqueue_task(
         &mut self,
         run_id: usize,
@@ -308,22 +550,37 @@ impl Backend for InMemoryBackend {
         scheduled_date_for_run: DateTime<Utc>,
         pipeline_name: String,
         is_dynamic: bool,
+        not_before: Option<DateTime<Utc>>,
     ) -> Result<()> {
         let depth = self.get_task_depth(run_id, task_id)?;
+        let task = self.get_task_by_id(run_id, task_id)?;
+        let priority_weight = self
+            .options
+            .priority_weight
+            .saturating_add(self.get_priority_weight(run_id, task_id)?);
         let mut priority_queue = self.priority_queue.lock();
 
         // remove previous attempts (this is needed for lazy expand)
-        priority_queue.retain(|x| x.queued_task.task_id != task_id);
+        priority_queue
+            .retain(|x| x.queued_task.run_id != run_id || x.queued_task.task_id != task_id);
         let attempt: usize = self.get_attempt_by_task_id(run_id, task_id, is_dynamic)?;
 
         priority_queue.push(OrderedQueuedTask {
-            score: depth,
+            score: get_queue_score(depth, priority_weight),
             queued_task: QueuedTask {
                 task_id,
                 run_id,
                 pipeline_name,
                 scheduled_date_for_run,
                 attempt,
+                not_before,
+                task_name: task.name,
+                max_concurrency: task.options.max_concurrency,
+                max_active_tasks: self.options.max_active_tasks,
+                pool: task.options.pool,
+                pool_slots: task.options.pool_slots,
+                priority_weight,
+                queue: task.options.queue,
             },
         });
         Ok(())
@@ -331,13 +588,18 @@ impl Backend for InMemoryBackend {
 
     fn take_last_stdout_line(
         &mut self,
-        _run_id: usize,
+        run_id: usize,
         task_id: usize,
         _attempt: usize,
     ) -> Result<Box<dyn Fn() -> Result<String> + Send>> {
         let task_logs = self.task_logs.clone();
         Ok(Box::new(move || {
-            Ok(task_logs.lock().entry(task_id).or_default().pop().unwrap())
+            Ok(task_logs
+                .lock()
+                .entry((run_id, task_id))
+                .or_default()
+                .pop()
+                .unwrap_or_default())
         }))
     }
 
@@ -357,4 +619,19 @@ impl Backend for InMemoryBackend {
     fn get_pipeline_path(&self) -> Result<String> {
         Ok(self.pipeline_path.to_string())
     }
+
+    fn supports_cancellation(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::backend_tests::backend_test_suite;
+
+    use super::InMemoryBackend;
+
+    backend_test_suite!(
+        |tasks, edges, options| InMemoryBackend::new("", tasks, edges).with_options(options)
+    );
 }
@@ -3,11 +3,14 @@ use std::env;
 use backend::Backend;
 
 pub mod backend;
+#[cfg(test)]
+mod backend_tests;
 pub mod blanket_backend;
 pub mod in_memory_backend;
 pub mod pipeline;
 pub mod pipeline_options;
 pub mod run;
+pub mod sqlite_backend;
 
 const DEFAULT_TPT_X_COMMAND: &str = "tpt_executor";
 
@@ -4,6 +4,7 @@ use chrono::TimeZone;
 use chrono::{DateTime, NaiveDateTime, Utc};
 use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
+use thepipelinetool_task::{retry_policy::RetryPolicy, Task};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct PipelineOptions {
@@ -19,6 +20,14 @@ pub struct PipelineOptions {
     #[serde(default)]
     pub retry_delay: Duration,
 
+    /// The retry policy of the tasks that don't set their own.
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
+
+    /// The retry jitter of the tasks that don't set their own.
+    #[serde(default)]
+    pub retry_jitter: Duration,
+
     #[serde(default)]
     pub timeout: Option<Duration>,
 
@@ -27,6 +36,34 @@ pub struct PipelineOptions {
 
     #[serde(default)]
     pub timezone: Option<Tz>,
+
+    #[serde(default)]
+    pub run_at: RunAt,
+
+    /// The most runs of the pipeline that can be active at once, including catchup runs.
+    #[serde(default)]
+    pub max_active_runs: Option<usize>,
+
+    /// The most tasks of the pipeline that can run at once, across all of its runs.
+    #[serde(default)]
+    pub max_active_tasks: Option<usize>,
+
+    /// Added to the priority weight of every task of the pipeline, so that its tasks are popped
+    /// before those of pipelines with a lower weight.
+    #[serde(default)]
+    pub priority_weight: usize,
+}
+
+/// When a scheduled run fires, relative to its data interval, which spans two ticks of the
+/// schedule.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum RunAt {
+    /// A run processes the interval that ends at its tick, once that data is complete.
+    #[default]
+    IntervalEnd,
+
+    /// A run processes the interval that starts at its tick.
+    IntervalStart,
 }
 
 impl Default for PipelineOptions {
@@ -36,9 +73,15 @@ impl Default for PipelineOptions {
             end_date: None,
             max_attempts: 1,
             retry_delay: Duration::ZERO,
+            retry_policy: RetryPolicy::Fixed,
+            retry_jitter: Duration::ZERO,
             timeout: None,
             catchup_date: None,
             timezone: None,
+            run_at: RunAt::IntervalEnd,
+            max_active_runs: None,
+            max_active_tasks: None,
+            priority_weight: 0,
         }
     }
 }
@@ -51,6 +94,19 @@ impl PipelineOptions {
     pub fn get_end_date_with_timezone(&self) -> Option<DateTime<Utc>> {
         naive_datetime_to_datetime_with_timezone(&self.end_date, &self.timezone)
     }
+
+    /// Gives the tasks that leave their retry policy or jitter at the default the pipeline-wide
+    /// ones.
+    pub fn apply_task_defaults(&self, tasks: &mut [Task]) {
+        for task in tasks {
+            if task.options.retry_policy == RetryPolicy::default() {
+                task.options.retry_policy = self.retry_policy;
+            }
+            if task.options.retry_jitter.is_zero() {
+                task.options.retry_jitter = self.retry_jitter;
+            }
+        }
+    }
 }
 
 fn naive_datetime_to_datetime_with_timezone(
@@ -6,6 +6,10 @@ pub struct Run {
     pub run_id: usize,
     pub pipeline_name: String,
     pub scheduled_date_for_run: DateTime<Utc>,
+
+    /// The window of data a scheduled run processes, between two ticks of the schedule.
+    #[serde(default)]
+    pub data_interval: Option<DataInterval>,
 }
 
 impl Run {
@@ -14,8 +18,24 @@ impl Run {
             run_id: 0,
             pipeline_name: "dummy".to_string(),
             scheduled_date_for_run: Utc::now(),
+            data_interval: None,
         }
     }
+
+    /// The data interval of the run, which is empty and at the scheduled date for runs that
+    /// weren't scheduled, like manual triggers.
+    pub fn get_data_interval(&self) -> DataInterval {
+        self.data_interval.clone().unwrap_or(DataInterval {
+            start: self.scheduled_date_for_run,
+            end: self.scheduled_date_for_run,
+        })
+    }
+}
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct DataInterval {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
 }
 
 #[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
@@ -25,4 +45,5 @@ pub enum RunStatus {
     Pending,
     Running,
     RetryPending,
+    Cancelled,
 }
@@ -0,0 +1,1006 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
+
+use crate::{
+    backend::{OriginalKey, ResultKey, UpstreamId},
+    blanket_backend::BlanketBackend,
+    pipeline::Pipeline,
+    pipeline_options::PipelineOptions,
+    run::{DataInterval, Run},
+    Backend,
+};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use parking_lot::{Mutex, MutexGuard};
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use serde_json::Value;
+use thepipelinetool_task::{
+    ordered_queued_task::get_queue_score, queued_task::QueuedTask, resource_pool::ResourcePool,
+    task_options::TaskOptions, task_result::TaskResult, task_status::TaskStatus,
+    temp_queued_task::TempQueuedTask, Task,
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS pipelines (
+        name TEXT PRIMARY KEY,
+        path TEXT NOT NULL,
+        options TEXT NOT NULL,
+        tasks TEXT NOT NULL,
+        edges TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS runs (
+        run_id INTEGER PRIMARY KEY,
+        pipeline_name TEXT NOT NULL,
+        run TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS active_runs (
+        run_id INTEGER PRIMARY KEY,
+        pipeline_name TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS data_intervals (
+        run_id INTEGER PRIMARY KEY,
+        data_interval TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS trigger_params (
+        run_id INTEGER PRIMARY KEY,
+        trigger_params TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS resource_pools (
+        name TEXT PRIMARY KEY,
+        resource_pool TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS tasks (
+        run_id INTEGER NOT NULL,
+        task_id INTEGER NOT NULL,
+        task TEXT NOT NULL,
+        PRIMARY KEY (run_id, task_id)
+    );
+    CREATE TABLE IF NOT EXISTS task_statuses (
+        run_id INTEGER NOT NULL,
+        task_id INTEGER NOT NULL,
+        status TEXT NOT NULL,
+        PRIMARY KEY (run_id, task_id)
+    );
+    CREATE TABLE IF NOT EXISTS task_results (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        run_id INTEGER NOT NULL,
+        task_id INTEGER NOT NULL,
+        result TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS logs (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        run_id INTEGER NOT NULL,
+        task_id INTEGER NOT NULL,
+        attempt INTEGER NOT NULL,
+        line TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS edges (
+        run_id INTEGER NOT NULL,
+        upstream_id INTEGER NOT NULL,
+        downstream_id INTEGER NOT NULL,
+        PRIMARY KEY (run_id, upstream_id, downstream_id)
+    );
+    CREATE TABLE IF NOT EXISTS dependencies (
+        run_id INTEGER NOT NULL,
+        task_id INTEGER NOT NULL,
+        upstream_id INTEGER NOT NULL,
+        original_key TEXT NOT NULL,
+        result_key TEXT NOT NULL,
+        PRIMARY KEY (run_id, task_id, upstream_id, original_key)
+    );
+    CREATE TABLE IF NOT EXISTS depths (
+        run_id INTEGER NOT NULL,
+        task_id INTEGER NOT NULL,
+        depth INTEGER NOT NULL,
+        PRIMARY KEY (run_id, task_id)
+    );
+    CREATE TABLE IF NOT EXISTS priority_weights (
+        run_id INTEGER NOT NULL,
+        task_id INTEGER NOT NULL,
+        priority_weight INTEGER NOT NULL,
+        PRIMARY KEY (run_id, task_id)
+    );
+    CREATE TABLE IF NOT EXISTS attempts (
+        run_id INTEGER NOT NULL,
+        task_id INTEGER NOT NULL,
+        is_dynamic INTEGER NOT NULL,
+        attempt INTEGER NOT NULL,
+        PRIMARY KEY (run_id, task_id, is_dynamic)
+    );
+    CREATE TABLE IF NOT EXISTS cleared_attempts (
+        run_id INTEGER NOT NULL,
+        task_id INTEGER NOT NULL,
+        attempts INTEGER NOT NULL,
+        PRIMARY KEY (run_id, task_id)
+    );
+    CREATE TABLE IF NOT EXISTS queue (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        score INTEGER NOT NULL,
+        run_id INTEGER NOT NULL,
+        task_id INTEGER NOT NULL,
+        not_before INTEGER,
+        queued_task TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS temp_queue (
+        temp_queued_task TEXT PRIMARY KEY
+    );
+";
+
+/// A `Backend` persisted in a single SQLite database file, for running the server and workers
+/// on one node without Redis. Every clone shares the same connection.
+#[derive(Clone)]
+pub struct SqliteBackend {
+    name: String,
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteBackend {
+    pub fn open(db_path: &str, pipeline_name: &str) -> Result<Self> {
+        Self::from_connection(Connection::open(db_path)?, pipeline_name)
+    }
+
+    pub fn open_in_memory(pipeline_name: &str) -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?, pipeline_name)
+    }
+
+    fn from_connection(conn: Connection, pipeline_name: &str) -> Result<Self> {
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        conn.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            name: pipeline_name.to_string(),
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock()
+    }
+
+    pub fn upload_pipeline(&self, pipeline: &Pipeline) -> Result<()> {
+        let mut tasks = pipeline.tasks.clone();
+        pipeline.options.apply_task_defaults(&mut tasks);
+
+        self.conn().execute(
+            "INSERT OR REPLACE INTO pipelines (name, path, options, tasks, edges)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                self.name,
+                pipeline.path,
+                serde_json::to_string(&pipeline.options)?,
+                serde_json::to_string(&tasks)?,
+                serde_json::to_string(&pipeline.edges)?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_pipelines(&self) -> Result<HashSet<String>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT name FROM pipelines")?;
+        let names = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<HashSet<String>>>()?;
+        Ok(names)
+    }
+
+    pub fn get_options(&self) -> Result<PipelineOptions> {
+        Ok(serde_json::from_str(&self.get_pipeline_column("options")?)?)
+    }
+
+    pub fn get_runs(&self) -> Result<Vec<Run>> {
+        let conn = self.conn();
+        let mut stmt =
+            conn.prepare("SELECT run FROM runs WHERE pipeline_name = ?1 ORDER BY run_id")?;
+        let members = stmt
+            .query_map([&self.name], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+
+        let mut v = vec![];
+        for s in members {
+            v.push(serde_json::from_str(&s)?);
+        }
+        Ok(v)
+    }
+
+    pub fn get_all_results(&self, run_id: usize, task_id: usize) -> Result<Vec<TaskResult>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT result FROM task_results WHERE run_id = ?1 AND task_id = ?2 ORDER BY id",
+        )?;
+        let members = stmt
+            .query_map(params![run_id, task_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+
+        let mut v = vec![];
+        for s in members {
+            v.push(serde_json::from_str(&s)?);
+        }
+        Ok(v)
+    }
+
+    pub fn get_temp_queue(&self) -> Result<Vec<TempQueuedTask>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT temp_queued_task FROM temp_queue")?;
+        let members = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+
+        let mut v = vec![];
+        for s in members {
+            v.push(serde_json::from_str(&s)?);
+        }
+        Ok(v)
+    }
+
+    pub fn get_running_tasks_count(&self) -> Result<usize> {
+        Ok(self
+            .conn()
+            .query_row("SELECT COUNT(*) FROM temp_queue", [], |row| row.get(0))?)
+    }
+
+    fn get_pipeline_column(&self, column: &str) -> Result<String> {
+        self.conn()
+            .query_row(
+                &format!("SELECT {column} FROM pipelines WHERE name = ?1"),
+                [&self.name],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| anyhow!("could not find pipeline '{}'", self.name))
+    }
+
+    fn get_edges(&self, run_id: usize) -> Result<Vec<(usize, usize)>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT upstream_id, downstream_id FROM edges WHERE run_id = ?1
+             ORDER BY upstream_id, downstream_id",
+        )?;
+        let edges = stmt
+            .query_map([run_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<(usize, usize)>>>()?;
+        Ok(edges)
+    }
+
+    fn new_run(
+        &self,
+        scheduled_date_for_run: DateTime<Utc>,
+        data_interval: Option<DataInterval>,
+        max_active_runs: Option<usize>,
+    ) -> Result<Option<Run>> {
+        let mut conn = self.conn();
+        // an immediate transaction keeps other processes from creating a run between the count
+        // and the insert
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        if let Some(max_active_runs) = max_active_runs {
+            let active_runs: usize = tx.query_row(
+                "SELECT COUNT(*) FROM active_runs WHERE pipeline_name = ?1",
+                [&self.name],
+                |row| row.get(0),
+            )?;
+            if active_runs >= max_active_runs {
+                return Ok(None);
+            }
+        }
+
+        let run_id: usize =
+            tx.query_row("SELECT COALESCE(MAX(run_id) + 1, 0) FROM runs", [], |row| {
+                row.get(0)
+            })?;
+        let run = Run {
+            run_id,
+            pipeline_name: self.name.to_string(),
+            scheduled_date_for_run,
+            data_interval,
+        };
+        tx.execute(
+            "INSERT INTO runs (run_id, pipeline_name, run) VALUES (?1, ?2, ?3)",
+            params![run_id, self.name, serde_json::to_string(&run)?],
+        )?;
+        tx.execute(
+            "INSERT INTO active_runs (run_id, pipeline_name) VALUES (?1, ?2)",
+            params![run_id, self.name],
+        )?;
+        tx.commit()?;
+
+        Ok(Some(run))
+    }
+}
+
+fn get_task_status(conn: &Connection, run_id: usize, task_id: usize) -> Result<TaskStatus> {
+    let status: Option<String> = conn
+        .query_row(
+            "SELECT status FROM task_statuses WHERE run_id = ?1 AND task_id = ?2",
+            params![run_id, task_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    Ok(match status {
+        Some(status) => serde_json::from_str(&status)?,
+        None => TaskStatus::Pending,
+    })
+}
+
+fn get_resource_pools(conn: &Connection) -> Result<BTreeMap<String, ResourcePool>> {
+    let mut stmt = conn.prepare("SELECT name, resource_pool FROM resource_pools")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<Vec<(String, String)>>>()?;
+    rows.into_iter()
+        .map(|(name, resource_pool)| Ok((name, serde_json::from_str(&resource_pool)?)))
+        .collect()
+}
+
+impl Backend for SqliteBackend {
+    fn get_pipeline_path(&self) -> Result<String> {
+        self.get_pipeline_column("path")
+    }
+
+    fn get_pipeline_name(&self) -> Result<String> {
+        Ok(self.name.to_string())
+    }
+
+    fn remove_from_temp_queue(&self, temp_queued_task: &TempQueuedTask) -> Result<()> {
+        self.conn().execute(
+            "DELETE FROM temp_queue WHERE temp_queued_task = ?1",
+            [serde_json::to_string(temp_queued_task)?],
+        )?;
+        Ok(())
+    }
+
+    fn get_queue_length(&self) -> Result<usize> {
+        Ok(self
+            .conn()
+            .query_row("SELECT COUNT(*) FROM queue", [], |row| row.get(0))?)
+    }
+
+    fn print_priority_queue(&mut self) -> Result<()> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare("SELECT score, queued_task FROM queue ORDER BY score, not_before, task_id")?;
+        for row in stmt.query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })? {
+            let (score, queued_task) = row?;
+            println!("{score}\t{queued_task}");
+        }
+        Ok(())
+    }
+
+    fn pop_priority_queue(&mut self) -> Result<Option<TempQueuedTask>> {
+        let mut conn = self.conn();
+        // an immediate transaction keeps other processes sharing the file from popping the same task
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let running = tx
+            .prepare("SELECT temp_queued_task FROM temp_queue")?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?
+            .iter()
+            .map(|temp_queued_task| serde_json::from_str(temp_queued_task))
+            .collect::<serde_json::Result<Vec<TempQueuedTask>>>()?;
+        let resource_pools = get_resource_pools(&tx)?;
+        let due = tx
+            .prepare(
+                "SELECT id, queued_task FROM queue WHERE not_before IS NULL OR not_before <= ?1
+                 ORDER BY score, not_before, task_id, id",
+            )?
+            .query_map([Utc::now().timestamp_millis()], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<(i64, String)>>>()?;
+
+        // tasks over their concurrency limits stay queued without blocking the others
+        let mut popped = None;
+        for (id, queued_task) in due {
+            let queued_task: QueuedTask = serde_json::from_str(&queued_task)?;
+            if !queued_task.is_saturated(running.iter().map(|t| &t.queued_task), &resource_pools) {
+                popped = Some((id, queued_task));
+                break;
+            }
+        }
+        let Some((id, queued_task)) = popped else {
+            return Ok(None);
+        };
+
+        let temp_queued_task = TempQueuedTask {
+            popped_date: Utc::now(),
+            queued_task,
+        };
+        tx.execute("DELETE FROM queue WHERE id = ?1", [id])?;
+        tx.execute(
+            "INSERT OR IGNORE INTO temp_queue (temp_queued_task) VALUES (?1)",
+            [serde_json::to_string(&temp_queued_task)?],
+        )?;
+        tx.commit()?;
+
+        Ok(Some(temp_queued_task))
+    }
+
+    fn remove_run_from_priority_queue(&mut self, run_id: usize) -> Result<()> {
+        self.conn()
+            .execute("DELETE FROM queue WHERE run_id = ?1", [run_id])?;
+        Ok(())
+    }
+
+    fn enqueue_task(
+        &mut self,
+        run_id: usize,
+        task_id: usize,
+        scheduled_date_for_run: DateTime<Utc>,
+        pipeline_name: String,
+        is_dynamic: bool,
+        not_before: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        let depth = self.get_task_depth(run_id, task_id)?;
+        let attempt = self.get_attempt_by_task_id(run_id, task_id, is_dynamic)?;
+        let task = self.get_task_by_id(run_id, task_id)?;
+        let options = self.get_options()?;
+        let priority_weight = options
+            .priority_weight
+            .saturating_add(self.get_priority_weight(run_id, task_id)?);
+        let conn = self.conn();
+
+        // remove previous attempts (this is needed for lazy expand)
+        conn.execute(
+            "DELETE FROM queue WHERE run_id = ?1 AND task_id = ?2",
+            params![run_id, task_id],
+        )?;
+        conn.execute(
+            "INSERT INTO queue (score, run_id, task_id, not_before, queued_task)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                get_queue_score(depth, priority_weight),
+                run_id,
+                task_id,
+                not_before.map(|not_before| not_before.timestamp_millis()),
+                serde_json::to_string(&QueuedTask {
+                    task_id,
+                    run_id,
+                    pipeline_name,
+                    scheduled_date_for_run,
+                    attempt,
+                    not_before,
+                    task_name: task.name,
+                    max_concurrency: task.options.max_concurrency,
+                    max_active_tasks: options.max_active_tasks,
+                    pool: task.options.pool,
+                    pool_slots: task.options.pool_slots,
+                    priority_weight,
+                    queue: task.options.queue,
+                })?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get_log(&mut self, run_id: usize, task_id: usize, attempt: usize) -> Result<String> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT line FROM logs WHERE run_id = ?1 AND task_id = ?2 AND attempt = ?3 ORDER BY id",
+        )?;
+        let lines = stmt
+            .query_map(params![run_id, task_id, attempt], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(lines.join(""))
+    }
+
+    fn get_log_handle_closure(
+        &mut self,
+        run_id: usize,
+        task_id: usize,
+        attempt: usize,
+    ) -> Result<Box<dyn Fn(String) -> Result<()> + Send>> {
+        let conn = self.conn.clone();
+        Ok(Box::new(move |s| {
+            conn.lock().execute(
+                "INSERT INTO logs (run_id, task_id, attempt, line) VALUES (?1, ?2, ?3, ?4)",
+                params![run_id, task_id, attempt, s],
+            )?;
+            Ok(())
+        }))
+    }
+
+    fn take_last_stdout_line(
+        &mut self,
+        run_id: usize,
+        task_id: usize,
+        attempt: usize,
+    ) -> Result<Box<dyn Fn() -> Result<String> + Send>> {
+        let conn = self.conn.clone();
+        Ok(Box::new(move || {
+            let conn = conn.lock();
+            let last: Option<(i64, String)> = conn
+                .query_row(
+                    "SELECT id, line FROM logs WHERE run_id = ?1 AND task_id = ?2 AND attempt = ?3
+                     ORDER BY id DESC LIMIT 1",
+                    params![run_id, task_id, attempt],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?;
+
+            Ok(match last {
+                Some((id, line)) => {
+                    conn.execute("DELETE FROM logs WHERE id = ?1", [id])?;
+                    line
+                }
+                None => "".to_string(),
+            })
+        }))
+    }
+
+    fn get_task_result(&mut self, run_id: usize, task_id: usize) -> Result<TaskResult> {
+        let result: String = self
+            .conn()
+            .query_row(
+                "SELECT result FROM task_results WHERE run_id = ?1 AND task_id = ?2
+                 ORDER BY id DESC LIMIT 1",
+                params![run_id, task_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| anyhow!("no result for run_id {run_id} and task_id {task_id}"))?;
+        Ok(serde_json::from_str(&result)?)
+    }
+
+    fn insert_task_results(&mut self, run_id: usize, result: &TaskResult) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO task_results (run_id, task_id, result) VALUES (?1, ?2, ?3)",
+            params![run_id, result.task_id, serde_json::to_string(result)?],
+        )?;
+        Ok(())
+    }
+
+    fn get_task_status(&self, run_id: usize, task_id: usize) -> Result<TaskStatus> {
+        get_task_status(&self.conn(), run_id, task_id)
+    }
+
+    fn get_task_status_closure(
+        &self,
+        run_id: usize,
+        task_id: usize,
+    ) -> Result<Box<dyn Fn() -> Result<TaskStatus> + Send>> {
+        let conn = self.conn.clone();
+        Ok(Box::new(move || {
+            get_task_status(&conn.lock(), run_id, task_id)
+        }))
+    }
+
+    fn supports_cancellation(&self) -> bool {
+        // the database can be shared with other processes
+        true
+    }
+
+    fn set_task_status(
+        &mut self,
+        run_id: usize,
+        task_id: usize,
+        task_status: TaskStatus,
+    ) -> Result<()> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO task_statuses (run_id, task_id, status) VALUES (?1, ?2, ?3)",
+            params![run_id, task_id, serde_json::to_string(&task_status)?],
+        )?;
+        Ok(())
+    }
+
+    fn get_downstream(&self, run_id: usize, task_id: usize) -> Result<Vec<usize>> {
+        Ok(self
+            .get_edges(run_id)?
+            .into_iter()
+            .filter(|(upstream_id, _)| *upstream_id == task_id)
+            .map(|(_, downstream_id)| downstream_id)
+            .collect())
+    }
+
+    fn get_upstream(&self, run_id: usize, task_id: usize) -> Result<Vec<usize>> {
+        Ok(self
+            .get_edges(run_id)?
+            .into_iter()
+            .filter(|(_, downstream_id)| *downstream_id == task_id)
+            .map(|(upstream_id, _)| upstream_id)
+            .collect())
+    }
+
+    fn get_default_tasks(&self) -> Result<Vec<Task>> {
+        Ok(serde_json::from_str(&self.get_pipeline_column("tasks")?)?)
+    }
+
+    fn get_all_tasks(&self, run_id: usize) -> Result<Vec<Task>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT task FROM tasks WHERE run_id = ?1 ORDER BY task_id")?;
+        let members = stmt
+            .query_map([run_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+
+        let mut tasks = vec![];
+        for m in members {
+            tasks.push(serde_json::from_str(&m)?);
+        }
+        Ok(tasks)
+    }
+
+    fn get_default_edges(&self) -> Result<HashSet<(usize, usize)>> {
+        Ok(serde_json::from_str(&self.get_pipeline_column("edges")?)?)
+    }
+
+    fn get_task_by_id(&self, run_id: usize, task_id: usize) -> Result<Task> {
+        let task: String = self
+            .conn()
+            .query_row(
+                "SELECT task FROM tasks WHERE run_id = ?1 AND task_id = ?2",
+                params![run_id, task_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| anyhow!("no task for run_id {run_id} and task_id {task_id}"))?;
+        Ok(serde_json::from_str(&task)?)
+    }
+
+    fn get_template_args(&self, run_id: usize, task_id: usize) -> Result<Value> {
+        Ok(self.get_task_by_id(run_id, task_id)?.template_args)
+    }
+
+    fn set_template_args(
+        &mut self,
+        run_id: usize,
+        task_id: usize,
+        template_args_str: &str,
+    ) -> Result<()> {
+        let mut task = self.get_task_by_id(run_id, task_id)?;
+        task.template_args = serde_json::from_str(template_args_str)?;
+
+        self.conn().execute(
+            "UPDATE tasks SET task = ?3 WHERE run_id = ?1 AND task_id = ?2",
+            params![run_id, task_id, serde_json::to_string(&task)?],
+        )?;
+        Ok(())
+    }
+
+    fn get_task_depth(&mut self, run_id: usize, task_id: usize) -> Result<usize> {
+        let depth: Option<usize> = self
+            .conn()
+            .query_row(
+                "SELECT depth FROM depths WHERE run_id = ?1 AND task_id = ?2",
+                params![run_id, task_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        if let Some(depth) = depth {
+            return Ok(depth);
+        }
+
+        let mut max_depth = 0;
+        for upstream_id in self.get_upstream(run_id, task_id)? {
+            let new_depth = self.get_task_depth(run_id, upstream_id)? + 1;
+            if new_depth > max_depth {
+                max_depth = new_depth;
+            }
+        }
+        self.set_task_depth(run_id, task_id, max_depth)?;
+        Ok(max_depth)
+    }
+
+    fn get_dependencies(
+        &mut self,
+        run_id: usize,
+        task_id: usize,
+    ) -> Result<HashMap<(UpstreamId, OriginalKey), ResultKey>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT upstream_id, original_key, result_key FROM dependencies
+             WHERE run_id = ?1 AND task_id = ?2",
+        )?;
+        let dependencies = stmt
+            .query_map(params![run_id, task_id], |row| {
+                Ok(((row.get(0)?, row.get(1)?), row.get(2)?))
+            })?
+            .collect::<rusqlite::Result<HashMap<(usize, String), String>>>()?;
+        Ok(dependencies)
+    }
+
+    fn set_dependency(
+        &mut self,
+        run_id: usize,
+        task_id: usize,
+        upstream: (UpstreamId, OriginalKey),
+        v: String,
+    ) -> Result<()> {
+        let (upstream_id, original_key) = upstream;
+        self.conn().execute(
+            "INSERT OR REPLACE INTO dependencies (run_id, task_id, upstream_id, original_key, result_key)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![run_id, task_id, upstream_id, original_key, v],
+        )?;
+        Ok(())
+    }
+
+    fn set_task_depth(&mut self, run_id: usize, task_id: usize, depth: usize) -> Result<()> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO depths (run_id, task_id, depth) VALUES (?1, ?2, ?3)",
+            params![run_id, task_id, depth],
+        )?;
+        Ok(())
+    }
+
+    fn delete_task_depth(&mut self, run_id: usize, task_id: usize) -> Result<()> {
+        self.conn().execute(
+            "DELETE FROM depths WHERE run_id = ?1 AND task_id = ?2",
+            params![run_id, task_id],
+        )?;
+        Ok(())
+    }
+
+    fn get_cached_priority_weight(&self, run_id: usize, task_id: usize) -> Result<Option<usize>> {
+        Ok(self
+            .conn()
+            .query_row(
+                "SELECT priority_weight FROM priority_weights WHERE run_id = ?1 AND task_id = ?2",
+                params![run_id, task_id],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    fn set_cached_priority_weight(
+        &mut self,
+        run_id: usize,
+        task_id: usize,
+        priority_weight: usize,
+    ) -> Result<()> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO priority_weights (run_id, task_id, priority_weight)
+             VALUES (?1, ?2, ?3)",
+            params![run_id, task_id, priority_weight],
+        )?;
+        Ok(())
+    }
+
+    fn delete_cached_priority_weight(&mut self, run_id: usize, task_id: usize) -> Result<()> {
+        self.conn().execute(
+            "DELETE FROM priority_weights WHERE run_id = ?1 AND task_id = ?2",
+            params![run_id, task_id],
+        )?;
+        Ok(())
+    }
+
+    fn get_attempt_by_task_id(
+        &self,
+        run_id: usize,
+        task_id: usize,
+        is_dynamic: bool,
+    ) -> Result<usize> {
+        Ok(self.conn().query_row(
+            "INSERT INTO attempts (run_id, task_id, is_dynamic, attempt) VALUES (?1, ?2, ?3, 1)
+             ON CONFLICT (run_id, task_id, is_dynamic) DO UPDATE SET attempt = attempt + 1
+             RETURNING attempt",
+            params![run_id, task_id, is_dynamic],
+            |row| row.get(0),
+        )?)
+    }
+
+    fn set_cleared_attempts(&mut self, run_id: usize, task_id: usize) -> Result<()> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO cleared_attempts (run_id, task_id, attempts)
+             SELECT ?1, ?2, COALESCE(MAX(attempt), 0) FROM attempts
+             WHERE run_id = ?1 AND task_id = ?2",
+            params![run_id, task_id],
+        )?;
+        Ok(())
+    }
+
+    fn get_cleared_attempts(&self, run_id: usize, task_id: usize) -> Result<usize> {
+        Ok(self
+            .conn()
+            .query_row(
+                "SELECT attempts FROM cleared_attempts WHERE run_id = ?1 AND task_id = ?2",
+                params![run_id, task_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .unwrap_or(0))
+    }
+
+    fn create_new_run(
+        &mut self,
+        scheduled_date_for_run: DateTime<Utc>,
+        data_interval: Option<DataInterval>,
+    ) -> Result<Run> {
+        Ok(self
+            .new_run(scheduled_date_for_run, data_interval, None)?
+            .expect("runs without a limit are always created"))
+    }
+
+    fn create_new_run_below(
+        &mut self,
+        scheduled_date_for_run: DateTime<Utc>,
+        data_interval: Option<DataInterval>,
+        max_active_runs: usize,
+    ) -> Result<Option<Run>> {
+        self.new_run(scheduled_date_for_run, data_interval, Some(max_active_runs))
+    }
+
+    fn set_data_interval(&mut self, run_id: usize, data_interval: &DataInterval) -> Result<()> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO data_intervals (run_id, data_interval) VALUES (?1, ?2)",
+            params![run_id, serde_json::to_string(data_interval)?],
+        )?;
+        Ok(())
+    }
+
+    fn get_active_run_ids(&mut self) -> Result<Vec<usize>> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare("SELECT run_id FROM active_runs WHERE pipeline_name = ?1 ORDER BY run_id")?;
+        let run_ids = stmt
+            .query_map([&self.name], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<usize>>>()?;
+        Ok(run_ids)
+    }
+
+    fn add_active_run_id(&mut self, run_id: usize) -> Result<()> {
+        self.conn().execute(
+            "INSERT OR IGNORE INTO active_runs (run_id, pipeline_name) VALUES (?1, ?2)",
+            params![run_id, self.name],
+        )?;
+        Ok(())
+    }
+
+    fn remove_active_run_id(&mut self, run_id: usize) -> Result<()> {
+        self.conn()
+            .execute("DELETE FROM active_runs WHERE run_id = ?1", [run_id])?;
+        Ok(())
+    }
+
+    fn get_resource_pools(&mut self) -> Result<BTreeMap<String, ResourcePool>> {
+        get_resource_pools(&self.conn())
+    }
+
+    fn set_resource_pool(&mut self, name: &str, resource_pool: &ResourcePool) -> Result<()> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO resource_pools (name, resource_pool) VALUES (?1, ?2)",
+            params![name, serde_json::to_string(resource_pool)?],
+        )?;
+        Ok(())
+    }
+
+    fn delete_resource_pool(&mut self, name: &str) -> Result<()> {
+        self.conn()
+            .execute("DELETE FROM resource_pools WHERE name = ?1", [name])?;
+        Ok(())
+    }
+
+    fn get_data_interval(&mut self, run_id: usize) -> Result<Option<DataInterval>> {
+        let data_interval: Option<String> = self
+            .conn()
+            .query_row(
+                "SELECT data_interval FROM data_intervals WHERE run_id = ?1",
+                [run_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(data_interval
+            .map(|data_interval| serde_json::from_str(&data_interval))
+            .transpose()?)
+    }
+
+    fn set_trigger_params(&mut self, run_id: usize, trigger_params: &Value) -> Result<()> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO trigger_params (run_id, trigger_params) VALUES (?1, ?2)",
+            params![run_id, serde_json::to_string(trigger_params)?],
+        )?;
+        Ok(())
+    }
+
+    fn get_trigger_params(&mut self, run_id: usize) -> Result<Value> {
+        let trigger_params: Option<String> = self
+            .conn()
+            .query_row(
+                "SELECT trigger_params FROM trigger_params WHERE run_id = ?1",
+                [run_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(trigger_params
+            .map(|trigger_params| serde_json::from_str(&trigger_params))
+            .transpose()?
+            .unwrap_or_default())
+    }
+
+    fn remove_edge(&mut self, run_id: usize, edge: (usize, usize)) -> Result<()> {
+        let (upstream_id, downstream_id) = edge;
+        let conn = self.conn();
+
+        conn.execute(
+            "DELETE FROM edges WHERE run_id = ?1 AND upstream_id = ?2 AND downstream_id = ?3",
+            params![run_id, upstream_id, downstream_id],
+        )?;
+        conn.execute(
+            "DELETE FROM dependencies WHERE run_id = ?1 AND task_id = ?2 AND upstream_id = ?3",
+            params![run_id, downstream_id, upstream_id],
+        )?;
+        Ok(())
+    }
+
+    fn insert_edge(&mut self, run_id: usize, edge: (usize, usize)) -> Result<()> {
+        let (upstream_id, downstream_id) = edge;
+        self.conn().execute(
+            "INSERT OR IGNORE INTO edges (run_id, upstream_id, downstream_id) VALUES (?1, ?2, ?3)",
+            params![run_id, upstream_id, downstream_id],
+        )?;
+        Ok(())
+    }
+
+    fn append_new_task_and_set_status_to_pending(
+        &mut self,
+        run_id: usize,
+        name: &str,
+        function_name: &str,
+        template_args: &Value,
+        options: &TaskOptions,
+        lazy_expand: bool,
+        is_dynamic: bool,
+        is_branch: bool,
+        use_trigger_params: bool,
+    ) -> Result<usize> {
+        let mut conn = self.conn();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let task_id: usize = tx.query_row(
+            "SELECT COALESCE(MAX(task_id) + 1, 0) FROM tasks WHERE run_id = ?1",
+            [run_id],
+            |row| row.get(0),
+        )?;
+        let task = Task {
+            id: task_id,
+            name: name.to_owned(),
+            function: function_name.to_owned(),
+            template_args: template_args.to_owned(),
+            options: options.to_owned(),
+            lazy_expand,
+            is_dynamic,
+            is_branch,
+            use_trigger_params,
+        };
+        tx.execute(
+            "INSERT INTO tasks (run_id, task_id, task) VALUES (?1, ?2, ?3)",
+            params![run_id, task_id, serde_json::to_string(&task)?],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO task_statuses (run_id, task_id, status) VALUES (?1, ?2, ?3)",
+            params![
+                run_id,
+                task_id,
+                serde_json::to_string(&TaskStatus::Pending)?
+            ],
+        )?;
+        tx.commit()?;
+
+        Ok(task_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{backend_tests::backend_test_suite, pipeline::Pipeline};
+
+    use super::SqliteBackend;
+
+    backend_test_suite!(|tasks, edges, options| {
+        let backend = SqliteBackend::open_in_memory("sqlite").unwrap();
+        backend
+            .upload_pipeline(&Pipeline {
+                path: "".to_string(),
+                options: options.clone(),
+                tasks: tasks.to_vec(),
+                edges: edges.clone(),
+            })
+            .unwrap();
+        backend
+    });
+}
@@ -41,12 +41,20 @@ async fn main() -> Result<()> {
         .nest_service("/", ServeDir::new(PathBuf::from("static")))
         .route("/ping", get(ping))
         .route("/pipelines", get(get_pipelines))
+        .route("/pools", get(get_resource_pools))
+        .route(
+            "/pools/:name",
+            post(set_resource_pool).delete(delete_resource_pool),
+        )
         .route("/runs/:pipeline_name", get(get_runs))
         .route("/runs/next/:pipeline_name", get(get_next_run))
         .route("/runs/last/:pipeline_name", get(get_last_run))
         .route("/runs/recent/:pipeline_name", get(get_recent_runs)) // TODO change to recent results?
         .route("/runs/all/:pipeline_name", get(get_runs_with_tasks))
+        .route("/runs/:run_id/cancel", post(cancel_run))
+        .route("/runs/:run_id/data_interval", get(get_data_interval))
         .route("/trigger/:pipeline_name", get(trigger).post(trigger_params))
+        .route("/clear/:pipeline_name/:run_id/:task_id", post(clear_task))
         .route("/statuses/:run_id", get(get_run_status))
         .route("/statuses/:run_id/:task_id", get(get_task_status))
         .route("/results/:run_id/:task_id", get(get_task_result))
@@ -61,7 +69,7 @@ async fn main() -> Result<()> {
         .route("/upload/:pipeline_name", post(upload_pipeline))
         .layer(
             CorsLayer::new()
-                .allow_methods([Method::GET, Method::POST])
+                .allow_methods([Method::GET, Method::POST, Method::DELETE])
                 .allow_origin(Any),
         )
         .layer(TraceLayer::new_for_http())
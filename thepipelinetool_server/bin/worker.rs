@@ -5,7 +5,7 @@ use thepipelinetool_runner::{backend::Backend, get_tpt_executor_command};
 use thepipelinetool_server::{
     env::{
         get_executor_image, get_executor_type, get_max_parallelism, get_redis_url,
-        get_worker_loop_interval,
+        get_worker_loop_interval, get_worker_queues,
     },
     get_redis_pool,
     redis_backend::RedisBackend,
@@ -23,11 +23,13 @@ async fn main() -> Result<()> {
 
     let max_parallelism = get_max_parallelism()?;
     let executor = get_executor_type()?;
-    let backend = RedisBackend::dummy(get_redis_pool()?);
+    let queues = get_worker_queues();
+    let backend = RedisBackend::dummy(get_redis_pool()?).with_queues(queues.clone());
     let loop_interval = Duration::from_millis(get_worker_loop_interval()?);
 
     println!("Running tpt worker with '{:?}' executor type", executor);
     println!("Connected to redis at {}", get_redis_url());
+    println!("Serving queues {:?}", queues);
 
     loop {
         let mut backend = backend.clone();
@@ -57,6 +59,8 @@ async fn work(
                 let _ = spawn(
                     cmd,
                     None,
+                    Duration::ZERO,
+                    None,
                     Box::new(|x| {
                         print!("{x}");
                         Ok(())
@@ -78,6 +82,8 @@ async fn work(
                 let _ = spawn(
                     cmd,
                     None,
+                    Duration::ZERO,
+                    None,
                     Box::new(|x| {
                         print!("{x}");
                         Ok(())
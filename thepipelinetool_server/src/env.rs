@@ -1,6 +1,7 @@
 use std::{env, process::Command};
 
 use serde_json::json;
+use thepipelinetool_core::dev::DEFAULT_QUEUE;
 use thepipelinetool_runner::get_tpt_executor_command;
 use thepipelinetool_utils::get_default_max_parallelism;
 
@@ -84,6 +85,43 @@ pub fn get_worker_loop_interval() -> Result<u64> {
         .parse::<u64>()?)
 }
 
+/// The comma separated queues a worker pops tasks from.
+pub fn get_worker_queues() -> Vec<String> {
+    parse_worker_queues(env::var("WORKER_QUEUES").ok())
+}
+
+fn parse_worker_queues(worker_queues: Option<String>) -> Vec<String> {
+    let queues: Vec<String> = worker_queues
+        .unwrap_or_default()
+        .split(',')
+        .map(|queue| queue.trim().to_string())
+        .filter(|queue| !queue.is_empty())
+        .collect();
+    // a worker always pops from some queue
+    if queues.is_empty() {
+        vec![DEFAULT_QUEUE.to_string()]
+    } else {
+        queues
+    }
+}
+
 pub fn get_executor_image() -> Result<String> {
     Ok(env::var("EXECUTOR_IMAGE").unwrap_or("executor".to_string()))
 }
+
+#[cfg(test)]
+mod test {
+    use super::parse_worker_queues;
+
+    #[test]
+    fn test_parse_worker_queues() {
+        assert_eq!(parse_worker_queues(None), vec!["default"]);
+        assert_eq!(parse_worker_queues(Some("".into())), vec!["default"]);
+        assert_eq!(parse_worker_queues(Some(" , ".into())), vec!["default"]);
+        assert_eq!(parse_worker_queues(Some("gpu".into())), vec!["gpu"]);
+        assert_eq!(
+            parse_worker_queues(Some(" default , gpu,,".into())),
+            vec!["default", "gpu"]
+        );
+    }
+}
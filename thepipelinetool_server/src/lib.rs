@@ -1,14 +1,14 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 
 use deadpool::Runtime;
 use deadpool_redis::{Config, Pool};
 use env::get_redis_url;
 use redis_backend::RedisBackend;
 use thepipelinetool_core::dev::*;
-use thepipelinetool_runner::run::{Run, RunStatus};
+use thepipelinetool_runner::run::{DataInterval, Run, RunStatus};
 use thepipelinetool_runner::{backend::Backend, blanket_backend::BlanketBackend};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 
 pub mod check_timeout;
 pub mod env;
@@ -47,6 +47,43 @@ pub fn _get_run_status(run_id: usize, pool: Pool) -> Result<RunStatus> {
     RedisBackend::dummy(pool).get_run_status(run_id)
 }
 
+pub fn _get_data_interval(run_id: usize, pool: Pool) -> Result<DataInterval> {
+    RedisBackend::dummy(pool)
+        .get_data_interval(run_id)?
+        .ok_or(anyhow!("run {run_id} has no data interval"))
+}
+
+pub fn _get_resource_pools(pool: Pool) -> Result<BTreeMap<String, ResourcePool>> {
+    RedisBackend::dummy(pool).get_resource_pools()
+}
+
+pub fn _set_resource_pool(name: &str, resource_pool: &ResourcePool, pool: Pool) -> Result<()> {
+    RedisBackend::dummy(pool).set_resource_pool(name, resource_pool)
+}
+
+pub fn _delete_resource_pool(name: &str, pool: Pool) -> Result<()> {
+    RedisBackend::dummy(pool).delete_resource_pool(name)
+}
+
+pub fn _cancel_run(run_id: usize, pool: Pool) -> Result<()> {
+    RedisBackend::dummy(pool).cancel_run(run_id)
+}
+
+pub async fn _clear_task(
+    pipeline_name: &str,
+    run_id: usize,
+    task_id: usize,
+    include_downstream: bool,
+    pool: Pool,
+) -> Result<()> {
+    let run = RedisBackend::get_runs(pipeline_name, pool.clone())
+        .await?
+        .into_iter()
+        .find(|run| run.run_id == run_id)
+        .ok_or_else(|| anyhow!("no run_id {run_id} for pipeline '{pipeline_name}'"))?;
+    RedisBackend::from(pipeline_name, pool).clear_task(&run, task_id, include_downstream)
+}
+
 pub fn _get_task_result(run_id: usize, task_id: usize, pool: Pool) -> Result<TaskResult> {
     RedisBackend::dummy(pool).get_task_result(run_id, task_id)
 }
@@ -1,9 +1,14 @@
-use deadpool_redis::{redis::cmd, Pool};
+use deadpool_redis::{
+    redis::{cmd, Script},
+    Pool,
+};
 use log::debug;
-use std::collections::{HashMap, HashSet};
-use thepipelinetool_runner::run::Run;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+use thepipelinetool_runner::run::{DataInterval, Run};
 use thepipelinetool_runner::{
-    backend::Backend, pipeline::Pipeline, pipeline_options::PipelineOptions,
+    backend::Backend, blanket_backend::BlanketBackend, pipeline::Pipeline,
+    pipeline_options::PipelineOptions,
 };
 
 use anyhow::{anyhow, Result};
@@ -17,9 +22,11 @@ const RUNS_KEY: &str = "runs";
 const NEXT_RUN_KEY: &str = "nr";
 const SCHEDULED_DATES_KEY: &str = "ld";
 const DEPTH_KEY: &str = "d";
+const PRIORITY_WEIGHT_KEY: &str = "pw";
 const TASK_RESULT_KEY: &str = "tr";
 const LOG_KEY: &str = "l";
 const TASK_ATTEMPT_KEY: &str = "a";
+const CLEARED_ATTEMPTS_KEY: &str = "ca";
 const DEPENDENCY_KEYS_KEY: &str = "dk";
 const EDGES_KEY: &str = "e";
 const TASKS_KEY: &str = "tks";
@@ -31,6 +38,56 @@ const DEFAULT_EDGES_KEY: &str = "de";
 const DEFAULT_OPTIONS_KEY: &str = "do";
 const PIPELINES_KEY: &str = "p";
 const PIPELINE_PATH_KEY: &str = "pp";
+const DATA_INTERVAL_KEY: &str = "di";
+const TRIGGER_PARAMS_KEY: &str = "tp";
+const ACTIVE_RUNS_KEY: &str = "ar";
+const RESOURCE_POOLS_KEY: &str = "rp";
+const POP_LOCK_KEY: &str = "pl";
+
+/// How long a worker can hold the pop lock before it expires on its own.
+const POP_LOCK_TIMEOUT_MS: usize = 5000;
+/// How many queued tasks are read at once while looking for one to pop.
+const POP_PAGE_SIZE: isize = 100;
+
+const RELEASE_LOCK_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+end
+return 0
+"#;
+
+const RENEW_LOCK_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("PEXPIRE", KEYS[1], ARGV[2])
+end
+return 0
+"#;
+
+/// Moves a queued task to the temp queue only while the pop lock is still held, so a worker whose
+/// lock expired during the scan can't start a task on a stale view of the running tasks.
+/// Returns -1 when the lock is lost and 0 when the task was already popped.
+const CLAIM_TASK_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) ~= ARGV[1] then
+    return -1
+end
+if redis.call("ZREM", KEYS[2], ARGV[2]) == 0 then
+    return 0
+end
+redis.call("SADD", KEYS[3], ARGV[3])
+return 1
+"#;
+
+/// Reserves a run id and marks it active unless the pipeline is at its active run limit
+/// (`ARGV[1]`, empty for no limit). Returns -1 when the limit is reached.
+const CREATE_RUN_SCRIPT: &str = r#"
+local max_active_runs = tonumber(ARGV[1])
+if max_active_runs and redis.call("SCARD", KEYS[1]) >= max_active_runs then
+    return -1
+end
+local run_id = redis.call("INCR", KEYS[2]) - 1
+redis.call("SADD", KEYS[1], run_id)
+return run_id
+"#;
 
 macro_rules! block_on {
     // Textual definition.
@@ -43,20 +100,33 @@ macro_rules! block_on {
 pub struct RedisBackend {
     name: Option<String>,
     pool: Pool,
+    /// The queues tasks are popped from, or all of them when `None`.
+    queues: Option<Vec<String>>,
 }
 
 impl RedisBackend {
     pub fn dummy(pool: Pool) -> Self {
-        Self { name: None, pool }
+        Self {
+            name: None,
+            pool,
+            queues: None,
+        }
     }
 
     pub fn from(pipeline_name: &str, pool: Pool) -> Self {
         Self {
             name: Some(pipeline_name.to_string()),
             pool,
+            queues: None,
         }
     }
 
+    /// Only pops tasks from these queues, see `TaskOptions::queue`.
+    pub fn with_queues(mut self, queues: Vec<String>) -> Self {
+        self.queues = Some(queues);
+        self
+    }
+
     #[timed(duration(printer = "debug!"))]
     pub async fn get_pipelines(pool: Pool) -> Result<HashSet<String>> {
         let mut conn = pool.get().await.expect("DB connection failed");
@@ -75,6 +145,8 @@ impl RedisBackend {
         pool: Pool,
     ) -> Result<()> {
         let mut conn = pool.get().await.expect("DB connection failed");
+        let mut tasks = pipeline.tasks.clone();
+        pipeline.options.apply_task_defaults(&mut tasks);
 
         cmd("SET")
             .arg(format!("{DEFAULT_OPTIONS_KEY}:{pipeline_name}"))
@@ -84,7 +156,7 @@ impl RedisBackend {
 
         cmd("SET")
             .arg(format!("{DEFAULT_TASKS_KEY}:{pipeline_name}"))
-            .arg(serde_json::to_string(&pipeline.tasks)?)
+            .arg(serde_json::to_string(&tasks)?)
             .query_async::<_, String>(&mut conn)
             .await?;
 
@@ -138,6 +210,159 @@ impl RedisBackend {
         Ok(v)
     }
 
+    /// Serializes pops across workers, so that the slots and concurrency limits they check
+    /// can't change between the check and the pop. Returns the token to release the lock with.
+    async fn lock_pop(&self) -> Result<String> {
+        let mut conn = self.pool.get().await.expect("DB connection failed");
+        let token = format!(
+            "{}:{}",
+            std::process::id(),
+            Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        );
+
+        while cmd("SET")
+            .arg(POP_LOCK_KEY)
+            .arg(&token)
+            .arg("NX")
+            .arg("PX")
+            .arg(POP_LOCK_TIMEOUT_MS)
+            .query_async::<_, Option<String>>(&mut conn)
+            .await?
+            .is_none()
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        Ok(token)
+    }
+
+    async fn unlock_pop(&self, token: &str) -> Result<()> {
+        let mut conn = self.pool.get().await.expect("DB connection failed");
+
+        Script::new(RELEASE_LOCK_SCRIPT)
+            .key(POP_LOCK_KEY)
+            .arg(token)
+            .invoke_async::<_, usize>(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    async fn new_run(
+        &self,
+        scheduled_date_for_run: DateTime<Utc>,
+        data_interval: Option<DataInterval>,
+        max_active_runs: Option<usize>,
+    ) -> Result<Option<Run>> {
+        let mut conn = self.pool.get().await.expect("DB connection failed");
+
+        let pipeline_name = self.get_pipeline_name()?;
+        let run_id = Script::new(CREATE_RUN_SCRIPT)
+            .key(format!("{ACTIVE_RUNS_KEY}:{pipeline_name}"))
+            .key("run")
+            .arg(max_active_runs.map(|m| m.to_string()).unwrap_or_default())
+            .invoke_async::<_, i64>(&mut conn)
+            .await?;
+        if run_id < 0 {
+            return Ok(None);
+        }
+
+        let run = Run {
+            run_id: run_id as usize,
+            pipeline_name: pipeline_name.to_string(),
+            scheduled_date_for_run,
+            data_interval,
+        };
+        cmd("RPUSH")
+            .arg(format!("{RUNS_KEY}:{pipeline_name}"))
+            .arg(serde_json::to_string(&run)?)
+            .query_async::<_, ()>(&mut conn)
+            .await?;
+
+        Ok(Some(run))
+    }
+
+    /// Extends the pop lock, returns false when it expired and another worker may hold it.
+    async fn renew_pop_lock(&self, token: &str) -> Result<bool> {
+        let mut conn = self.pool.get().await.expect("DB connection failed");
+
+        Ok(Script::new(RENEW_LOCK_SCRIPT)
+            .key(POP_LOCK_KEY)
+            .arg(token)
+            .arg(POP_LOCK_TIMEOUT_MS)
+            .invoke_async::<_, usize>(&mut conn)
+            .await?
+            == 1)
+    }
+
+    async fn pop_unlocked(&self, token: &str) -> Result<Option<TempQueuedTask>> {
+        let mut conn = self.pool.get().await.expect("DB connection failed");
+
+        let now = Utc::now();
+        let running = self.get_temp_queue().await?;
+        let resource_pools = self.get_resource_pools_async().await?;
+
+        // the queue is read in pages by score, so the scan stops at the first task that can run
+        let mut start = 0;
+        loop {
+            if !self.renew_pop_lock(token).await? {
+                return Ok(None);
+            }
+            let members = cmd("ZRANGE")
+                .arg("queue")
+                .arg(start)
+                .arg(start + POP_PAGE_SIZE - 1)
+                .query_async::<_, Vec<String>>(&mut conn)
+                .await?;
+            if members.is_empty() {
+                return Ok(None);
+            }
+            start += POP_PAGE_SIZE;
+
+            for member in members {
+                let queued_task: QueuedTask = serde_json::from_str(&member)?;
+                // tasks over their concurrency limits stay queued without blocking the others
+                if !queued_task.is_served_by(self.queues.as_deref())
+                    || !queued_task.is_due(now)
+                    || queued_task
+                        .is_saturated(running.iter().map(|t| &t.queued_task), &resource_pools)
+                {
+                    continue;
+                }
+                let temp_queued_task = TempQueuedTask {
+                    popped_date: now,
+                    queued_task,
+                };
+                match Script::new(CLAIM_TASK_SCRIPT)
+                    .key(POP_LOCK_KEY)
+                    .key("queue")
+                    .key("tmpqueue")
+                    .arg(token)
+                    .arg(member)
+                    .arg(serde_json::to_string(&temp_queued_task)?)
+                    .invoke_async::<_, i64>(&mut conn)
+                    .await?
+                {
+                    -1 => return Ok(None),
+                    // the task was removed from the queue since it was read
+                    0 => continue,
+                    _ => return Ok(Some(temp_queued_task)),
+                }
+            }
+        }
+    }
+
+    #[timed(duration(printer = "debug!"))]
+    pub async fn get_resource_pools_async(&self) -> Result<BTreeMap<String, ResourcePool>> {
+        let mut conn = self.pool.get().await.expect("DB connection failed");
+
+        cmd("HGETALL")
+            .arg(RESOURCE_POOLS_KEY)
+            .query_async::<_, HashMap<String, String>>(&mut conn)
+            .await?
+            .into_iter()
+            .map(|(name, resource_pool)| Ok((name, serde_json::from_str(&resource_pool)?)))
+            .collect()
+    }
+
     #[timed(duration(printer = "debug!"))]
     pub async fn get_all_results(
         run_id: usize,
@@ -309,6 +534,52 @@ impl Backend for RedisBackend {
         })
     }
 
+    #[timed(duration(printer = "debug!"))]
+    fn get_cached_priority_weight(&self, run_id: usize, task_id: usize) -> Result<Option<usize>> {
+        block_on!({
+            let mut conn = self.pool.get().await.expect("DB connection failed");
+
+            Ok(cmd("GET")
+                .arg(format!("{PRIORITY_WEIGHT_KEY}:{run_id}:{task_id}"))
+                .query_async::<_, Option<usize>>(&mut conn)
+                .await?)
+        })
+    }
+
+    #[timed(duration(printer = "debug!"))]
+    fn set_cached_priority_weight(
+        &mut self,
+        run_id: usize,
+        task_id: usize,
+        priority_weight: usize,
+    ) -> Result<()> {
+        block_on!({
+            let mut conn = self.pool.get().await.expect("DB connection failed");
+
+            cmd("SET")
+                .arg(format!("{PRIORITY_WEIGHT_KEY}:{run_id}:{task_id}"))
+                .arg(priority_weight)
+                .query_async::<_, ()>(&mut conn)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    #[timed(duration(printer = "debug!"))]
+    fn delete_cached_priority_weight(&mut self, run_id: usize, task_id: usize) -> Result<()> {
+        block_on!({
+            let mut conn = self.pool.get().await.expect("DB connection failed");
+
+            cmd("DEL")
+                .arg(format!("{PRIORITY_WEIGHT_KEY}:{run_id}:{task_id}"))
+                .query_async::<_, usize>(&mut conn)
+                .await?;
+
+            Ok(())
+        })
+    }
+
     #[timed(duration(printer = "debug!"))]
     fn get_log(&mut self, run_id: usize, task_id: usize, attempt: usize) -> Result<String> {
         block_on!({
@@ -378,6 +649,45 @@ impl Backend for RedisBackend {
         })
     }
 
+    #[timed(duration(printer = "debug!"))]
+    fn set_cleared_attempts(&mut self, run_id: usize, task_id: usize) -> Result<()> {
+        block_on!({
+            let mut conn = self.pool.get().await.expect("DB connection failed");
+
+            let mut attempts = 0;
+            for is_dynamic in [false, true] {
+                attempts = attempts.max(
+                    cmd("GET")
+                        .arg(format!(
+                            "{TASK_ATTEMPT_KEY}:{run_id}:{task_id}:{is_dynamic}"
+                        ))
+                        .query_async::<_, Option<usize>>(&mut conn)
+                        .await?
+                        .unwrap_or(0),
+                );
+            }
+            cmd("SET")
+                .arg(format!("{CLEARED_ATTEMPTS_KEY}:{run_id}:{task_id}"))
+                .arg(attempts)
+                .query_async::<_, ()>(&mut conn)
+                .await?;
+            Ok(())
+        })
+    }
+
+    #[timed(duration(printer = "debug!"))]
+    fn get_cleared_attempts(&self, run_id: usize, task_id: usize) -> Result<usize> {
+        block_on!({
+            let mut conn = self.pool.get().await.expect("DB connection failed");
+
+            Ok(cmd("GET")
+                .arg(format!("{CLEARED_ATTEMPTS_KEY}:{run_id}:{task_id}"))
+                .query_async::<_, Option<usize>>(&mut conn)
+                .await?
+                .unwrap_or(0))
+        })
+    }
+
     #[timed(duration(printer = "debug!"))]
     fn get_task_status(&self, run_id: usize, task_id: usize) -> Result<TaskStatus> {
         block_on!({
@@ -391,6 +701,19 @@ impl Backend for RedisBackend {
         })
     }
 
+    fn get_task_status_closure(
+        &self,
+        run_id: usize,
+        task_id: usize,
+    ) -> Result<Box<dyn Fn() -> Result<TaskStatus> + Send>> {
+        let backend = self.clone();
+        Ok(Box::new(move || backend.get_task_status(run_id, task_id)))
+    }
+
+    fn supports_cancellation(&self) -> bool {
+        true
+    }
+
     #[timed(duration(printer = "debug!"))]
     fn set_task_status(
         &mut self,
@@ -411,30 +734,165 @@ impl Backend for RedisBackend {
     }
 
     #[timed(duration(printer = "debug!"))]
-    fn create_new_run(&mut self, scheduled_date_for_run: DateTime<Utc>) -> Result<Run> {
+    fn create_new_run(
+        &mut self,
+        scheduled_date_for_run: DateTime<Utc>,
+        data_interval: Option<DataInterval>,
+    ) -> Result<Run> {
+        block_on!({
+            Ok(self
+                .new_run(scheduled_date_for_run, data_interval, None)
+                .await?
+                .expect("runs without a limit are always created"))
+        })
+    }
+
+    #[timed(duration(printer = "debug!"))]
+    fn create_new_run_below(
+        &mut self,
+        scheduled_date_for_run: DateTime<Utc>,
+        data_interval: Option<DataInterval>,
+        max_active_runs: usize,
+    ) -> Result<Option<Run>> {
+        block_on!({
+            self.new_run(scheduled_date_for_run, data_interval, Some(max_active_runs))
+                .await
+        })
+    }
+
+    #[timed(duration(printer = "debug!"))]
+    fn set_data_interval(&mut self, run_id: usize, data_interval: &DataInterval) -> Result<()> {
         block_on!({
             let mut conn = self.pool.get().await.expect("DB connection failed");
+            cmd("SET")
+                .arg(format!("{DATA_INTERVAL_KEY}:{run_id}"))
+                .arg(serde_json::to_string(data_interval)?)
+                .query_async::<_, ()>(&mut conn)
+                .await?;
 
-            let run_id = cmd("INCR")
-                .arg("run")
-                .query_async::<_, usize>(&mut conn)
-                .await?
-                - 1;
+            Ok(())
+        })
+    }
 
+    #[timed(duration(printer = "debug!"))]
+    fn get_active_run_ids(&mut self) -> Result<Vec<usize>> {
+        block_on!({
+            let mut conn = self.pool.get().await.expect("DB connection failed");
             let pipeline_name = self.get_pipeline_name()?;
-            let run = Run {
-                run_id,
-                pipeline_name: pipeline_name.to_string(),
-                scheduled_date_for_run,
-            };
 
-            cmd("RPUSH")
-                .arg(format!("{RUNS_KEY}:{pipeline_name}"))
-                .arg(serde_json::to_string(&run)?)
+            Ok(cmd("SMEMBERS")
+                .arg(format!("{ACTIVE_RUNS_KEY}:{pipeline_name}"))
+                .query_async::<_, Vec<usize>>(&mut conn)
+                .await?)
+        })
+    }
+
+    #[timed(duration(printer = "debug!"))]
+    fn add_active_run_id(&mut self, run_id: usize) -> Result<()> {
+        block_on!({
+            let mut conn = self.pool.get().await.expect("DB connection failed");
+            let pipeline_name = self.get_pipeline_name()?;
+
+            cmd("SADD")
+                .arg(format!("{ACTIVE_RUNS_KEY}:{pipeline_name}"))
+                .arg(run_id)
+                .query_async::<_, ()>(&mut conn)
+                .await?;
+            Ok(())
+        })
+    }
+
+    #[timed(duration(printer = "debug!"))]
+    fn remove_active_run_id(&mut self, run_id: usize) -> Result<()> {
+        block_on!({
+            let mut conn = self.pool.get().await.expect("DB connection failed");
+            let pipeline_name = self.get_pipeline_name()?;
+
+            cmd("SREM")
+                .arg(format!("{ACTIVE_RUNS_KEY}:{pipeline_name}"))
+                .arg(run_id)
+                .query_async::<_, ()>(&mut conn)
+                .await?;
+            Ok(())
+        })
+    }
+
+    #[timed(duration(printer = "debug!"))]
+    fn get_resource_pools(&mut self) -> Result<BTreeMap<String, ResourcePool>> {
+        block_on!({ self.get_resource_pools_async().await })
+    }
+
+    #[timed(duration(printer = "debug!"))]
+    fn set_resource_pool(&mut self, name: &str, resource_pool: &ResourcePool) -> Result<()> {
+        block_on!({
+            let mut conn = self.pool.get().await.expect("DB connection failed");
+
+            cmd("HSET")
+                .arg(RESOURCE_POOLS_KEY)
+                .arg(name)
+                .arg(serde_json::to_string(resource_pool)?)
+                .query_async::<_, ()>(&mut conn)
+                .await?;
+            Ok(())
+        })
+    }
+
+    #[timed(duration(printer = "debug!"))]
+    fn delete_resource_pool(&mut self, name: &str) -> Result<()> {
+        block_on!({
+            let mut conn = self.pool.get().await.expect("DB connection failed");
+
+            cmd("HDEL")
+                .arg(RESOURCE_POOLS_KEY)
+                .arg(name)
                 .query_async::<_, ()>(&mut conn)
                 .await?;
+            Ok(())
+        })
+    }
+
+    #[timed(duration(printer = "debug!"))]
+    fn get_data_interval(&mut self, run_id: usize) -> Result<Option<DataInterval>> {
+        block_on!({
+            let mut conn = self.pool.get().await.expect("DB connection failed");
+            let data_interval = cmd("GET")
+                .arg(format!("{DATA_INTERVAL_KEY}:{run_id}"))
+                .query_async::<_, Option<String>>(&mut conn)
+                .await?;
+
+            Ok(data_interval
+                .map(|data_interval| serde_json::from_str(&data_interval))
+                .transpose()?)
+        })
+    }
+
+    #[timed(duration(printer = "debug!"))]
+    fn set_trigger_params(&mut self, run_id: usize, trigger_params: &Value) -> Result<()> {
+        block_on!({
+            let mut conn = self.pool.get().await.expect("DB connection failed");
+            cmd("SET")
+                .arg(format!("{TRIGGER_PARAMS_KEY}:{run_id}"))
+                .arg(serde_json::to_string(trigger_params)?)
+                .query_async::<_, ()>(&mut conn)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    #[timed(duration(printer = "debug!"))]
+    fn get_trigger_params(&mut self, run_id: usize) -> Result<Value> {
+        block_on!({
+            let mut conn = self.pool.get().await.expect("DB connection failed");
+            let trigger_params = cmd("GET")
+                .arg(format!("{TRIGGER_PARAMS_KEY}:{run_id}"))
+                .query_async::<_, Option<String>>(&mut conn)
+                .await?;
 
-            Ok(run)
+            Ok(trigger_params
+                .map(|trigger_params| serde_json::from_str(&trigger_params))
+                .transpose()?
+                .unwrap_or_default())
         })
     }
 
@@ -733,34 +1191,35 @@ impl Backend for RedisBackend {
 
     // #[timed(duration(printer = "debug!"))]
     fn pop_priority_queue(&mut self) -> Result<Option<TempQueuedTask>> {
+        block_on!({
+            let token = self.lock_pop().await?;
+            let popped = self.pop_unlocked(&token).await;
+            self.unlock_pop(&token).await?;
+            popped
+        })
+    }
+
+    #[timed(duration(printer = "debug!"))]
+    fn remove_run_from_priority_queue(&mut self, run_id: usize) -> Result<()> {
         block_on!({
             let mut conn = self.pool.get().await.expect("DB connection failed");
 
-            let res = cmd("ZPOPMIN")
-                .arg(&["queue".to_string(), "1".to_string()]) // TODO timeout arg
+            let members = cmd("ZRANGEBYSCORE")
+                .arg("queue")
+                .arg("-inf")
+                .arg("+inf")
                 .query_async::<_, Vec<String>>(&mut conn)
-                .await;
-
-            if let Ok(vec) = &res {
-                if !vec.is_empty() {
-                    let temp_queued_task = TempQueuedTask {
-                        popped_date: Utc::now(),
-                        queued_task: serde_json::from_str(&vec[0])?,
-                    };
-                    cmd("SADD")
-                        .arg(&[
-                            "tmpqueue".to_string(),
-                            serde_json::to_string(&temp_queued_task)?,
-                        ])
-                        .query_async::<_, ()>(&mut conn)
+                .await?;
+            for m in members {
+                let queued_task: QueuedTask = serde_json::from_str(&m)?;
+                if queued_task.run_id == run_id {
+                    cmd("ZREM")
+                        .arg(&["queue".to_string(), m])
+                        .query_async::<_, usize>(&mut conn)
                         .await?;
-                    return Ok(Some(temp_queued_task));
                 }
-            } else {
-                println!("{:#?}", res.unwrap_err().detail());
             }
-
-            Ok(None)
+            Ok(())
         })
     }
 
@@ -813,11 +1272,19 @@ impl Backend for RedisBackend {
         scheduled_date_for_run: DateTime<Utc>,
         pipeline_name: String,
         is_dynamic: bool,
+        not_before: Option<DateTime<Utc>>,
     ) -> Result<()> {
         block_on!({
             let depth = self.get_task_depth(run_id, task_id)?;
             let mut conn = self.pool.get().await.expect("DB connection failed");
             let attempt: usize = self.get_attempt_by_task_id(run_id, task_id, is_dynamic)?;
+            let task = self.get_task_by_id(run_id, task_id)?;
+            let options = RedisBackend::from(&pipeline_name, self.pool.clone())
+                .get_options()
+                .await?;
+            let priority_weight = options
+                .priority_weight
+                .saturating_add(self.get_priority_weight(run_id, task_id)?);
 
             // remove previous attempts (this is needed for lazy expand)
             let members = cmd("ZRANGEBYSCORE")
@@ -838,13 +1305,21 @@ impl Backend for RedisBackend {
             cmd("ZADD")
                 .arg(&[
                     "queue".to_string(),
-                    depth.to_string(),
+                    get_queue_score(depth, priority_weight).to_string(),
                     serde_json::to_string(&QueuedTask {
                         task_id,
                         run_id,
                         pipeline_name,
                         scheduled_date_for_run,
                         attempt,
+                        not_before,
+                        task_name: task.name,
+                        max_concurrency: task.options.max_concurrency,
+                        max_active_tasks: options.max_active_tasks,
+                        pool: task.options.pool,
+                        pool_slots: task.options.pool_slots,
+                        priority_weight,
+                        queue: task.options.queue,
                     })?,
                 ])
                 .query_async::<_, usize>(&mut conn)
@@ -906,3 +1381,78 @@ impl Backend for RedisBackend {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use deadpool_redis::redis::cmd;
+    use thepipelinetool_core::dev::*;
+
+    use super::{RedisBackend, POP_LOCK_KEY};
+    use crate::get_redis_pool;
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "needs a redis server at REDIS_URL and adds to its queue"]
+    async fn test_pop_needs_the_lock() {
+        let pool = get_redis_pool().unwrap();
+        let mut conn = pool.get().await.unwrap();
+        let backend = RedisBackend::from("test_pop_needs_the_lock", pool.clone());
+        let queued_task = QueuedTask {
+            task_id: 0,
+            run_id: 0,
+            pipeline_name: "test_pop_needs_the_lock".into(),
+            scheduled_date_for_run: Utc::now(),
+            attempt: 1,
+            not_before: None,
+            task_name: "load".into(),
+            max_concurrency: None,
+            max_active_tasks: None,
+            pool: None,
+            pool_slots: 1,
+            priority_weight: 1,
+            queue: DEFAULT_QUEUE.into(),
+        };
+        let member = serde_json::to_string(&queued_task).unwrap();
+        cmd("ZADD")
+            .arg("queue")
+            .arg("-inf")
+            .arg(&member)
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .unwrap();
+
+        // the lock expired during the scan and another worker holds it now
+        let token = backend.lock_pop().await.unwrap();
+        cmd("SET")
+            .arg(POP_LOCK_KEY)
+            .arg("other worker")
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .unwrap();
+        assert!(backend.pop_unlocked(&token).await.unwrap().is_none());
+        assert!(cmd("ZSCORE")
+            .arg("queue")
+            .arg(&member)
+            .query_async::<_, Option<f64>>(&mut conn)
+            .await
+            .unwrap()
+            .is_some());
+        cmd("DEL")
+            .arg(POP_LOCK_KEY)
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .unwrap();
+
+        let token = backend.lock_pop().await.unwrap();
+        let popped = backend.pop_unlocked(&token).await.unwrap().unwrap();
+        backend.unlock_pop(&token).await.unwrap();
+        assert_eq!(popped.queued_task, queued_task);
+
+        cmd("SREM")
+            .arg("tmpqueue")
+            .arg(serde_json::to_string(&popped).unwrap())
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .unwrap();
+    }
+}
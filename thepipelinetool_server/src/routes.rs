@@ -1,12 +1,12 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use axum::{
-    extract::{self, Path, State},
+    extract::{self, Path, Query, State},
     http::StatusCode,
     Json,
 };
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use thepipelinetool_core::dev::*;
 use thepipelinetool_runner::pipeline::Pipeline;
 
@@ -234,6 +234,18 @@ pub async fn get_run_status(
     })?))
 }
 
+pub async fn get_data_interval(
+    Path(run_id): Path<usize>,
+    State(pool): State<Pool>,
+) -> ServerResult<Json<DataInterval>> {
+    Ok(Json(_get_data_interval(run_id, pool).map_err(|e| {
+        service_err(format!(
+            "could not get data interval for run_id '{}'\n{:?}",
+            run_id, e
+        ))
+    })?))
+}
+
 pub async fn get_task_result(
     Path((run_id, task_id)): Path<(usize, usize)>,
     State(pool): State<Pool>,
@@ -335,6 +347,35 @@ pub async fn get_default_graph(
     Ok(json!(get_default_graphite_graph(&tasks, &edges)).into())
 }
 
+pub async fn create_run_below_max_active_runs(
+    pipeline_name: &str,
+    backend: &mut RedisBackend,
+    scheduled_date: DateTime<Utc>,
+) -> ServerResult<Run> {
+    let max_active_runs = backend
+        .get_options()
+        .await
+        .map_err(|e| service_err(format!("could not get pipeline options\n{:?}", e)))?
+        .max_active_runs;
+    backend
+        .try_create_new_run(scheduled_date, None, max_active_runs)
+        .map_err(|e| {
+            service_err(format!(
+                "could not create new run for pipeline '{}'\n{:?}",
+                pipeline_name, e
+            ))
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                format!(
+                    "pipeline already has {} active runs\n",
+                    max_active_runs.unwrap_or_default()
+                ),
+            )
+        })
+}
+
 pub async fn assert_pipeline_exists(pipeline_name: &str, pool: Pool) -> ServerResult<()> {
     if !_get_pipelines(pool)
         .await
@@ -358,15 +399,16 @@ pub async fn trigger(
 
     let scheduled_date = Utc::now();
     let mut backend = RedisBackend::from(&pipeline_name, pool.clone());
-    let run = backend.create_new_run(scheduled_date).map_err(|e| {
-        service_err(format!(
-            "could not create new run for pipeline '{}'\n{:?}",
-            pipeline_name, e
-        ))
-    })?;
+    let run =
+        create_run_below_max_active_runs(&pipeline_name, &mut backend, scheduled_date).await?;
     let run_id = run.run_id;
 
-    tokio::spawn(async move { backend.enqueue_run(&run, None) });
+    tokio::spawn(async move {
+        // the run gives up its max_active_runs slot when this fails
+        if let Err(e) = backend.enqueue_run(&run, None) {
+            eprintln!("could not enqueue run {run_id}\n{:?}", e);
+        }
+    });
 
     Ok(run_id.into())
 }
@@ -380,19 +422,80 @@ pub async fn trigger_params(
 
     let scheduled_date = Utc::now();
     let mut backend = RedisBackend::from(&pipeline_name, pool.clone());
-    let run = backend.create_new_run(scheduled_date).map_err(|e| {
-        service_err(format!(
-            "could not create new run for pipeline '{}'\n{:?}",
-            pipeline_name, e
-        ))
-    })?;
+    let run =
+        create_run_below_max_active_runs(&pipeline_name, &mut backend, scheduled_date).await?;
     let run_id = run.run_id;
 
-    tokio::spawn(async move { backend.enqueue_run(&run, Some(params)) });
+    tokio::spawn(async move {
+        // the run gives up its max_active_runs slot when this fails
+        if let Err(e) = backend.enqueue_run(&run, Some(params)) {
+            eprintln!("could not enqueue run {run_id}\n{:?}", e);
+        }
+    });
 
     Ok(run_id.into())
 }
 
+pub async fn get_resource_pools(
+    State(pool): State<Pool>,
+) -> ServerResult<Json<BTreeMap<String, ResourcePool>>> {
+    Ok(Json(_get_resource_pools(pool).map_err(|e| {
+        service_err(format!("could not get pools\n{:?}", e))
+    })?))
+}
+
+pub async fn set_resource_pool(
+    Path(name): Path<String>,
+    State(pool): State<Pool>,
+    extract::Json(resource_pool): extract::Json<ResourcePool>,
+) -> ServerResult<String> {
+    _set_resource_pool(&name, &resource_pool, pool)
+        .map_err(|e| service_err(format!("could not set pool '{}'\n{:?}", name, e)))?;
+    Ok("ok".to_string())
+}
+
+pub async fn delete_resource_pool(
+    Path(name): Path<String>,
+    State(pool): State<Pool>,
+) -> ServerResult<String> {
+    _delete_resource_pool(&name, pool)
+        .map_err(|e| service_err(format!("could not delete pool '{}'\n{:?}", name, e)))?;
+    Ok("ok".to_string())
+}
+
+pub async fn cancel_run(
+    Path(run_id): Path<usize>,
+    State(pool): State<Pool>,
+) -> ServerResult<String> {
+    _cancel_run(run_id, pool)
+        .map_err(|e| service_err(format!("could not cancel run_id '{}'\n{:?}", run_id, e)))?;
+    Ok("ok".to_string())
+}
+
+#[derive(Deserialize)]
+pub struct ClearParams {
+    #[serde(default)]
+    downstream: bool,
+}
+
+pub async fn clear_task(
+    Path((pipeline_name, run_id, task_id)): Path<(String, usize, usize)>,
+    Query(params): Query<ClearParams>,
+    State(pool): State<Pool>,
+) -> ServerResult<String> {
+    assert_pipeline_exists(&pipeline_name, pool.clone()).await?;
+
+    _clear_task(&pipeline_name, run_id, task_id, params.downstream, pool)
+        .await
+        .map_err(|e| {
+            service_err(format!(
+                "could not clear task_id '{}' in run_id '{}'\n{:?}",
+                task_id, run_id, e
+            ))
+        })?;
+    Ok("ok".to_string())
+}
+
 pub async fn upload_pipeline(
     Path(pipeline_name): Path<String>,
     State(pool): State<Pool>,
@@ -1,10 +1,12 @@
 use std::{collections::HashSet, sync::Arc, time::Duration};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
 
 use deadpool_redis::Pool;
 use saffron::{Cron, CronTimesIter};
-use thepipelinetool_runner::{backend::Backend, blanket_backend::BlanketBackend};
+use thepipelinetool_runner::{
+    blanket_backend::BlanketBackend, pipeline_options::RunAt, run::DataInterval,
+};
 use tokio::{sync::Mutex, time::sleep};
 
 use anyhow::Result;
@@ -58,6 +60,8 @@ pub async fn scheduler(pool: Pool) -> Result<()> {
                             .unwrap_or(Utc::now()),
                     ),
                     options.get_end_date_with_timezone(),
+                    options.run_at,
+                    options.max_active_runs,
                     pool.clone(),
                 )
                 .await;
@@ -74,9 +78,29 @@ pub async fn _scheduler(
     cron: &Cron,
     scheduled_dates: CronTimesIter,
     end_date: Option<DateTime<Utc>>,
+    run_at: RunAt,
+    max_active_runs: Option<usize>,
     pool: Pool,
 ) -> Result<()> {
+    let loop_interval = Duration::new(get_scheduler_loop_interval()?, 0);
+
+    let mut previous_date = None;
+
     for scheduled_date in scheduled_dates {
+        let data_interval = match run_at {
+            RunAt::IntervalEnd => DataInterval {
+                start: previous_date
+                    .or_else(|| get_previous_date(cron, scheduled_date))
+                    .unwrap_or(scheduled_date),
+                end: scheduled_date,
+            },
+            RunAt::IntervalStart => DataInterval {
+                start: scheduled_date,
+                end: cron.next_after(scheduled_date).unwrap_or(scheduled_date),
+            },
+        };
+        previous_date = Some(scheduled_date);
+
         if !cron.contains(scheduled_date) {
             // TODO check if we need this?
             println!("Failed check! Cron does not contain {}.", scheduled_date);
@@ -104,7 +128,17 @@ pub async fn _scheduler(
         }
 
         let mut backend = RedisBackend::from(pipeline_name, pool.clone());
-        let run = backend.create_new_run(scheduled_date)?;
+        // catchup waits for earlier runs to finish instead of flooding the queue
+        let run = loop {
+            if let Some(run) = backend.try_create_new_run(
+                scheduled_date,
+                Some(data_interval.clone()),
+                max_active_runs,
+            )? {
+                break run;
+            }
+            sleep(loop_interval).await;
+        };
         backend.enqueue_run(&run, None)?;
         println!(
             "scheduling catchup {pipeline_name} {}",
@@ -118,3 +152,20 @@ pub async fn _scheduler(
 
     Ok(())
 }
+
+/// The last date of the schedule before `date`, found by looking further and further back.
+fn get_previous_date(cron: &Cron, date: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let mut lookback = ChronoDuration::minutes(1);
+
+    // leap days are the rarest dates a schedule can have
+    while lookback < ChronoDuration::days(366 * 8) {
+        if let Some(mut previous_date) = cron.next_from(date - lookback).filter(|d| *d < date) {
+            while let Some(next_date) = cron.next_after(previous_date).filter(|d| *d < date) {
+                previous_date = next_date;
+            }
+            return Some(previous_date);
+        }
+        lookback = lookback * 2;
+    }
+    None
+}
@@ -1,7 +1,147 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 #[derive(Serialize)]
 pub enum Branch<T: Serialize> {
     Left(T),
     Right(T),
 }
+
+/// Returned by a switch task: the name of the downstream arm to run, and the value passed to it.
+#[derive(Serialize, Deserialize)]
+pub struct Switch<T: Serialize> {
+    pub arm: String,
+    pub value: T,
+}
+
+impl<T: Serialize> Switch<T> {
+    pub fn new(arm: &str, value: T) -> Self {
+        Self {
+            arm: arm.to_string(),
+            value,
+        }
+    }
+}
+
+/// The downstream arm selected by a branch or switch task.
+#[derive(Debug, PartialEq)]
+pub enum ChosenArm {
+    /// The first downstream task of a `Branch`.
+    Left,
+    /// The second downstream task of a `Branch`.
+    Right,
+    /// The downstream task with this name.
+    Named(String),
+    /// The downstream tasks with these names.
+    Names(Vec<String>),
+}
+
+impl ChosenArm {
+    /// Splits the result of a branch task into the chosen arm and the value passed on to it.
+    ///
+    /// Accepts a serialized `Branch` or `Switch`, or a bare string or list of strings naming the
+    /// arms, as printed by a script. Any other result selects no arm.
+    pub fn from_result(result: Value) -> (Option<Self>, Value) {
+        match result {
+            Value::Object(mut map) => {
+                if let Some(value) = map.remove("Left") {
+                    (Some(ChosenArm::Left), value)
+                } else if let Some(value) = map.remove("Right") {
+                    (Some(ChosenArm::Right), value)
+                } else if let Some(Value::String(arm)) = map.get("arm") {
+                    let arm = arm.clone();
+                    let value = map.remove("value").unwrap_or_default();
+                    (Some(ChosenArm::Named(arm)), value)
+                } else {
+                    (None, Value::Object(map))
+                }
+            }
+            Value::String(arm) => (Some(ChosenArm::Named(arm.clone())), Value::String(arm)),
+            Value::Array(arms) if arms.iter().all(Value::is_string) => {
+                let names = arms
+                    .iter()
+                    .map(|arm| arm.as_str().unwrap().to_string())
+                    .collect();
+                (Some(ChosenArm::Names(names)), Value::Array(arms))
+            }
+            result => (None, result),
+        }
+    }
+
+    /// Whether this names the arm `arm_name` of the branch task `branch_name`.
+    ///
+    /// Names are relative to the task group of the branch task, so `load` names the arm
+    /// `extract/load` of `extract/choose`. Full names match too.
+    pub fn names(&self, arm_name: &str, branch_name: &str) -> bool {
+        let relative_name = match branch_name.rsplit_once('/') {
+            Some((group, _)) => arm_name
+                .strip_prefix(group)
+                .and_then(|name| name.strip_prefix('/')),
+            None => None,
+        };
+        let is_name = |name: &String| {
+            name == arm_name || relative_name.is_some_and(|relative_name| name == relative_name)
+        };
+
+        match self {
+            ChosenArm::Named(name) => is_name(name),
+            ChosenArm::Names(names) => names.iter().any(is_name),
+            ChosenArm::Left | ChosenArm::Right => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::{json, Value};
+
+    use super::{Branch, ChosenArm, Switch};
+
+    #[test]
+    fn test_from_result() {
+        assert_eq!(
+            ChosenArm::from_result(json!(Branch::Left(1))),
+            (Some(ChosenArm::Left), json!(1))
+        );
+        assert_eq!(
+            ChosenArm::from_result(json!(Branch::Right("a"))),
+            (Some(ChosenArm::Right), json!("a"))
+        );
+        assert_eq!(
+            ChosenArm::from_result(json!(Switch::new("load", [1, 2]))),
+            (Some(ChosenArm::Named("load".into())), json!([1, 2]))
+        );
+        assert_eq!(
+            ChosenArm::from_result(json!("load")),
+            (Some(ChosenArm::Named("load".into())), json!("load"))
+        );
+        assert_eq!(
+            ChosenArm::from_result(json!(["load", "notify"])),
+            (
+                Some(ChosenArm::Names(vec!["load".into(), "notify".into()])),
+                json!(["load", "notify"])
+            )
+        );
+        assert_eq!(ChosenArm::from_result(json!([1, 2])), (None, json!([1, 2])));
+        assert_eq!(
+            ChosenArm::from_result(json!({"a": 1})),
+            (None, json!({"a": 1}))
+        );
+        assert_eq!(ChosenArm::from_result(Value::Null), (None, Value::Null));
+    }
+
+    #[test]
+    fn test_names() {
+        let load = ChosenArm::Named("load".into());
+        assert!(load.names("load", "choose"));
+        assert!(load.names("extract/load", "extract/choose"));
+        assert!(load.names("extract/parse/load", "extract/parse/choose"));
+        assert!(!load.names("extract/load", "choose"));
+        assert!(!load.names("other/load", "extract/choose"));
+        assert!(!load.names("extract/parse/load", "extract/choose"));
+        assert!(ChosenArm::Named("extract/load".into()).names("extract/load", "extract/choose"));
+        assert!(ChosenArm::Names(vec!["a".into(), "load".into()])
+            .names("extract/load", "extract/choose"));
+        assert!(!ChosenArm::Left.names("load", "choose"));
+    }
+}
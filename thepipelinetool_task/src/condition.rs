@@ -0,0 +1,278 @@
+//! Conditions like `{{check.count}} > 0 and {{params.full_refresh}} != true`, used by
+//! `TaskOptions::when`.
+//!
+//! `{{name.key}}` is a template expression (see [`crate::template`]) evaluated against the
+//! context, which holds upstream results by task name, and is `null` when the path doesn't exist.
+//! Referring to a name that is neither an upstream task nor a run context name is an error.
+//! Literals are numbers, quoted strings, `true`, `false` and `null`. Comparisons are `==`, `!=`,
+//! `<`, `<=`, `>` and `>=`, combined with `and`, `or`, `not` and parentheses. A value on its own
+//! is false when it's `null`, `false`, `0`, or an empty string, array or object.
+
+use std::cmp::Ordering;
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+
+use crate::template::Expression;
+
+pub fn evaluate_condition(condition: &str, context: &Value) -> Result<bool> {
+    let mut parser = Parser {
+        tokens: tokenize(condition)?,
+        position: 0,
+        context,
+    };
+    let value = parser.or()?;
+    if let Some(token) = parser.tokens.get(parser.position) {
+        bail!("unexpected {token:?} in condition '{condition}'");
+    }
+    Ok(is_truthy(&value))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Reference(String),
+    Literal(Value),
+    Operator(String),
+    And,
+    Or,
+    Not,
+    LeftParen,
+    RightParen,
+}
+
+fn tokenize(condition: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut rest = condition.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        let (token, len) = if let Some(inner) = rest.strip_prefix("{{") {
+            let end = inner
+                .find("}}")
+                .ok_or_else(|| anyhow!("unclosed '{{{{' in condition '{condition}'"))?;
+            (Token::Reference(inner[..end].trim().to_string()), end + 4)
+        } else if c == '(' {
+            (Token::LeftParen, 1)
+        } else if c == ')' {
+            (Token::RightParen, 1)
+        } else if c == '\'' || c == '"' {
+            let end = rest[1..]
+                .find(c)
+                .ok_or_else(|| anyhow!("unclosed string in condition '{condition}'"))?;
+            (
+                Token::Literal(Value::String(rest[1..=end].to_string())),
+                end + 2,
+            )
+        } else if let Some(operator) = ["==", "!=", "<=", ">=", "&&", "||", "<", ">", "!"]
+            .into_iter()
+            .find(|operator| rest.starts_with(operator))
+        {
+            let token = match operator {
+                "&&" => Token::And,
+                "||" => Token::Or,
+                "!" => Token::Not,
+                _ => Token::Operator(operator.to_string()),
+            };
+            (token, operator.len())
+        } else {
+            let len = rest
+                .find(|c: char| c.is_whitespace() || "(){}<>=!&|'\"".contains(c))
+                .unwrap_or(rest.len());
+            let word = &rest[..len];
+            let token = match word {
+                "and" => Token::And,
+                "or" => Token::Or,
+                "not" => Token::Not,
+                _ => Token::Literal(
+                    serde_json::from_str(word)
+                        .map_err(|_| anyhow!("unexpected '{word}' in condition '{condition}'"))?,
+                ),
+            };
+            (token, len.max(1))
+        };
+        tokens.push(token);
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    context: &'a Value,
+}
+
+impl Parser<'_> {
+    fn next_if(&mut self, token: &Token) -> bool {
+        if self.tokens.get(self.position) == Some(token) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn or(&mut self) -> Result<Value> {
+        let mut value = self.and()?;
+        while self.next_if(&Token::Or) {
+            let right = self.and()?;
+            value = Value::Bool(is_truthy(&value) || is_truthy(&right));
+        }
+        Ok(value)
+    }
+
+    fn and(&mut self) -> Result<Value> {
+        let mut value = self.not()?;
+        while self.next_if(&Token::And) {
+            let right = self.not()?;
+            value = Value::Bool(is_truthy(&value) && is_truthy(&right));
+        }
+        Ok(value)
+    }
+
+    fn not(&mut self) -> Result<Value> {
+        if self.next_if(&Token::Not) {
+            return Ok(Value::Bool(!is_truthy(&self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Value> {
+        let left = self.operand()?;
+        let Some(Token::Operator(operator)) = self.tokens.get(self.position).cloned() else {
+            return Ok(left);
+        };
+        self.position += 1;
+        let right = self.operand()?;
+
+        let ordering = compare(&left, &right);
+        Ok(Value::Bool(match operator.as_str() {
+            "==" => ordering == Some(Ordering::Equal),
+            "!=" => ordering != Some(Ordering::Equal),
+            _ => {
+                let ordering =
+                    ordering.ok_or_else(|| anyhow!("can't compare {left} {operator} {right}"))?;
+                match operator.as_str() {
+                    "<" => ordering.is_lt(),
+                    "<=" => ordering.is_le(),
+                    ">" => ordering.is_gt(),
+                    _ => ordering.is_ge(),
+                }
+            }
+        }))
+    }
+
+    fn operand(&mut self) -> Result<Value> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| anyhow!("condition ended unexpectedly"))?;
+        self.position += 1;
+
+        match token {
+            Token::Reference(expression) => {
+                let expression = Expression::parse(&expression)?;
+                if self.context.get(&expression.root).is_none() {
+                    bail!(
+                        "'{}' in condition is not an upstream task or a run context name",
+                        expression.root
+                    );
+                }
+                expression.evaluate_or_null(self.context)
+            }
+            Token::Literal(value) => Ok(value),
+            Token::LeftParen => {
+                let value = self.or()?;
+                if !self.next_if(&Token::RightParen) {
+                    bail!("missing ')' in condition");
+                }
+                Ok(value)
+            }
+            token => bail!("unexpected {token:?} in condition"),
+        }
+    }
+}
+
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => left.as_f64()?.partial_cmp(&right.as_f64()?),
+        (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+        (left, right) if left == right => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(value) => *value,
+        Value::Number(number) => number.as_f64() != Some(0.0),
+        Value::String(string) => !string.is_empty(),
+        Value::Array(array) => !array.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::evaluate_condition;
+
+    #[test]
+    fn test_evaluate_condition() {
+        let context = json!({
+            "check": { "count": 3, "files": ["a.csv"], "status": "ok" },
+            "empty": "",
+            "params": { "full_refresh": true },
+        });
+        for (condition, expected) in [
+            ("{{check.count}} > 0", true),
+            ("{{ check.count }} >= 3.0", true),
+            ("{{check.count}} < 3", false),
+            ("{{check.status}} == 'ok'", true),
+            ("{{check.status}} != \"ok\"", false),
+            ("{{check.files.0}} == 'a.csv'", true),
+            ("{{check.files}}", true),
+            ("{{empty}}", false),
+            ("not {{params.full_refresh}}", false),
+            ("!{{params.full_refresh}} || {{check.count}} == 3", true),
+            (
+                "{{check.count}} > 5 or ({{check.count}} > 1 and {{empty}} == '')",
+                true,
+            ),
+            (
+                "{{check.count}} > 1 && {{params.full_refresh}} == false",
+                false,
+            ),
+            ("true", true),
+            ("null", false),
+            ("{{params.missing}} == null", true),
+            ("{{check.count.missing}}", false),
+            ("{{check.files | length}} == 1", true),
+            ("{{params.missing | default: 3}} >= 3", true),
+        ] {
+            assert_eq!(
+                evaluate_condition(condition, &context).unwrap(),
+                expected,
+                "{condition}"
+            );
+        }
+
+        for condition in [
+            "{{missing}} > 0",
+            "{{missing}} == null",
+            "not {{missing.count}}",
+            "{{missing | default: 3}} >= 3",
+            "{{check.status}} > 1",
+            "{{check.count}} >",
+            "({{check.count}} > 1",
+            "{{check.count",
+            "count > 1",
+        ] {
+            assert!(
+                evaluate_condition(condition, &context).is_err(),
+                "{condition}"
+            );
+        }
+    }
+}
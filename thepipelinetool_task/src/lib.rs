@@ -1,22 +1,29 @@
-use std::{env, ffi::OsStr, fs, path::PathBuf, process::Command, thread};
+use std::{env, ffi::OsStr, fs, path::PathBuf, process::Command};
 
 use anyhow::Result;
 use chrono::Utc;
+use run_context::RunContext;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use task_options::TaskOptions;
 use task_result::TaskResult;
-use thepipelinetool_utils::{spawn, value_from_file, value_to_file};
+use thepipelinetool_utils::{get_exit_code, spawn, value_from_file, value_to_file, Interruption};
 
 pub mod branch;
+pub mod condition;
 pub mod ordered_queued_task;
 pub mod queued_task;
+pub mod resource_pool;
+pub mod retry_policy;
+pub mod run_context;
 pub mod task_options;
 pub mod task_ref_inner;
 pub mod task_result;
 pub mod task_status;
 pub mod temp_queued_task;
+pub mod template;
 pub mod trigger_rule;
+pub mod weight_rule;
 
 fn get_json_dir() -> String {
     env::var("JSON_DIR")
@@ -53,9 +60,10 @@ impl Task {
         handle_stdout_log: Box<dyn Fn(String) -> Result<()> + Send>,
         handle_stderr_log: Box<dyn Fn(String) -> Result<()> + Send>,
         take_last_stdout_line: Box<dyn Fn() -> Result<String> + Send>,
+        is_cancelled: Option<Box<dyn Fn() -> bool + Send>>,
         pipeline_path: P,
         tpt_path: D,
-        run_id: usize,
+        run_context: &RunContext,
     ) -> Result<TaskResult>
     where
         P: AsRef<OsStr>,
@@ -67,7 +75,7 @@ impl Task {
         let mut cmd = Command::new(tpt_path);
         cmd.arg(pipeline_path);
         cmd.args(["run", "function", &self.function]);
-        cmd.env("run_id", run_id.to_string());
+        cmd.envs(run_context.to_env());
 
         let out_path: Option<PathBuf> = if get_save_to_file() {
             let json_dir = get_json_dir();
@@ -86,26 +94,32 @@ impl Task {
             None
         };
 
-        if attempt > 1 {
-            thread::sleep(self.options.retry_delay);
-        }
         let start = Utc::now();
 
-        // TODO store exit code? (coudl allow for 'skipped' status)
         let exit_status = spawn(
             cmd,
             self.options.timeout,
+            self.options.timeout_grace_period,
+            is_cancelled,
             handle_stdout_log,
             handle_stderr_log,
         );
-        let (success, code) = match exit_status {
-            Ok(exit_status) => (exit_status.success(), exit_status.code()),
-            Err(_) => (false, Some(124)),
+        let (success, code, interruption) = match exit_status {
+            Ok((exit_status, interruption)) => (
+                interruption.is_none()
+                    && exit_status
+                        .code()
+                        .is_some_and(|code| self.options.is_success_exit_code(code)),
+                get_exit_code(&exit_status),
+                interruption,
+            ),
+            Err(_) => (false, None, None),
         };
-        let timed_out = code == Some(124);
         let end = Utc::now();
 
         let result = match (success, get_save_to_file()) {
+            // a command that exited with one of the extra success codes leaves no result behind
+            (true, _) if code != Some(0) => Value::Null,
             (true, true) => value_from_file(&out_path.unwrap()).unwrap(),
             (true, false) => serde_json::from_str(&take_last_stdout_line().unwrap()).unwrap(),
             (false, _) => Value::Null,
@@ -124,10 +138,16 @@ impl Task {
             ended: Some(end),
             elapsed: end.timestamp() - start.timestamp(),
             premature_failure: false,
-            premature_failure_error_str: if timed_out { "timed out" } else { "" }.into(),
+            premature_failure_error_str: match interruption {
+                Some(Interruption::TimedOut) => "timed out",
+                Some(Interruption::Cancelled) => "cancelled",
+                None => "",
+            }
+            .into(),
             is_branch: self.is_branch,
             is_sensor: self.options.is_sensor,
             exit_code: code,
+            skipped: !success && interruption.is_none() && self.options.is_skip_exit_code(code),
         })
     }
 }
@@ -2,9 +2,24 @@ use serde::{Deserialize, Serialize};
 
 use crate::queued_task::QueuedTask;
 
+/// Weights are spaced further apart than any task depth, so that a higher weight always wins.
+const PRIORITY_WEIGHT_STEP: i64 = 1 << 20;
+
+/// Higher priority weights are clamped to this, so that queue scores can't overflow.
+pub const MAX_PRIORITY_WEIGHT: usize = (i64::MAX / PRIORITY_WEIGHT_STEP) as usize;
+
+/// The score of a queued task, lowest first: tasks with a higher priority weight, then tasks
+/// closer to the start of their run.
+pub fn get_queue_score(depth: usize, priority_weight: usize) -> i64 {
+    let depth = i64::try_from(depth).unwrap_or(i64::MAX);
+    let priority_weight = priority_weight.min(MAX_PRIORITY_WEIGHT) as i64;
+    depth.saturating_sub(priority_weight.saturating_mul(PRIORITY_WEIGHT_STEP))
+}
+
 #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct OrderedQueuedTask {
-    pub score: usize,
+    /// From [`get_queue_score`].
+    pub score: i64,
     pub queued_task: QueuedTask,
 }
 
@@ -13,6 +28,12 @@ impl Ord for OrderedQueuedTask {
         other
             .score
             .cmp(&self.score)
+            .then_with(|| {
+                other
+                    .queued_task
+                    .not_before
+                    .cmp(&self.queued_task.not_before)
+            })
             .then_with(|| other.queued_task.task_id.cmp(&self.queued_task.task_id))
     }
 
@@ -36,3 +57,59 @@ impl PartialOrd for OrderedQueuedTask {
         Some(self.cmp(other))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::BinaryHeap;
+
+    use chrono::Utc;
+
+    use super::{get_queue_score, OrderedQueuedTask, MAX_PRIORITY_WEIGHT};
+    use crate::queued_task::QueuedTask;
+
+    #[test]
+    fn test_priority_weight() {
+        let ordered_queued_task = |task_id, depth, priority_weight| OrderedQueuedTask {
+            score: get_queue_score(depth, priority_weight),
+            queued_task: QueuedTask {
+                task_id,
+                run_id: 0,
+                pipeline_name: "etl".into(),
+                scheduled_date_for_run: Utc::now(),
+                attempt: 1,
+                not_before: None,
+                task_name: "".into(),
+                max_concurrency: None,
+                max_active_tasks: None,
+                pool: None,
+                pool_slots: 1,
+                priority_weight,
+                queue: "default".into(),
+            },
+        };
+        let mut queue = BinaryHeap::from([
+            ordered_queued_task(0, 0, 1),
+            ordered_queued_task(1, 3, 10),
+            ordered_queued_task(2, 1, 1),
+            ordered_queued_task(3, 0, 10),
+        ]);
+
+        let mut popped = vec![];
+        while let Some(ordered_queued_task) = queue.pop() {
+            popped.push(ordered_queued_task.queued_task.task_id);
+        }
+        assert_eq!(popped, [3, 1, 0, 2]);
+    }
+
+    #[test]
+    fn test_max_priority_weight() {
+        // weights past the maximum are clamped instead of overflowing, and still order by depth
+        assert!(get_queue_score(0, usize::MAX) < get_queue_score(0, MAX_PRIORITY_WEIGHT - 1));
+        assert_eq!(
+            get_queue_score(0, usize::MAX),
+            get_queue_score(0, MAX_PRIORITY_WEIGHT)
+        );
+        assert!(get_queue_score(0, usize::MAX) < get_queue_score(1, usize::MAX));
+        assert!(get_queue_score(usize::MAX, 0) > get_queue_score(0, 0));
+    }
+}
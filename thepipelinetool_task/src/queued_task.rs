@@ -1,6 +1,13 @@
+use std::collections::BTreeMap;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    resource_pool::ResourcePool,
+    task_options::{default_pool_slots, default_queue},
+};
+
 #[derive(Debug, Eq, PartialEq, Deserialize, Serialize, Hash, Clone)]
 pub struct QueuedTask {
     pub task_id: usize,
@@ -8,4 +15,184 @@ pub struct QueuedTask {
     pub pipeline_name: String,
     pub scheduled_date_for_run: DateTime<Utc>,
     pub attempt: usize,
+
+    /// The task is not popped from the queue before this date.
+    #[serde(default)]
+    pub not_before: Option<DateTime<Utc>>,
+
+    /// Instances of the same task, in every run of the pipeline, share its name.
+    #[serde(default)]
+    pub task_name: String,
+
+    /// From `TaskOptions::max_concurrency`.
+    #[serde(default)]
+    pub max_concurrency: Option<usize>,
+
+    /// From `PipelineOptions::max_active_tasks`.
+    #[serde(default)]
+    pub max_active_tasks: Option<usize>,
+
+    /// From `TaskOptions::pool`.
+    #[serde(default)]
+    pub pool: Option<String>,
+
+    /// From `TaskOptions::pool_slots`.
+    #[serde(default = "default_pool_slots")]
+    pub pool_slots: usize,
+
+    /// The weight of the task following its `WeightRule`, plus `PipelineOptions::priority_weight`.
+    #[serde(default)]
+    pub priority_weight: usize,
+
+    /// From `TaskOptions::queue`.
+    #[serde(default = "default_queue")]
+    pub queue: String,
+}
+
+impl QueuedTask {
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.not_before.is_none_or(|not_before| not_before <= now)
+    }
+
+    /// Whether a worker popping from `queues` picks up the task, workers without queues take
+    /// every task.
+    pub fn is_served_by(&self, queues: Option<&[String]>) -> bool {
+        queues.is_none_or(|queues| queues.contains(&self.queue))
+    }
+
+    /// Whether running the task now would exceed its pipeline's `max_active_tasks`, its own
+    /// `max_concurrency` or the free slots of its pool, given the tasks that are running.
+    pub fn is_saturated<'a>(
+        &self,
+        running: impl IntoIterator<Item = &'a QueuedTask>,
+        resource_pools: &BTreeMap<String, ResourcePool>,
+    ) -> bool {
+        let resource_pool = self.pool.as_ref().and_then(|pool| resource_pools.get(pool));
+        let mut pipeline_tasks = 0;
+        let mut instances = 0;
+        let mut used_slots = 0;
+
+        for queued_task in running {
+            if resource_pool.is_some() && queued_task.pool == self.pool {
+                used_slots += queued_task.pool_slots;
+            }
+            if queued_task.pipeline_name != self.pipeline_name {
+                continue;
+            }
+            pipeline_tasks += 1;
+            if queued_task.task_name == self.task_name {
+                instances += 1;
+            }
+        }
+
+        self.max_active_tasks
+            .is_some_and(|max_active_tasks| pipeline_tasks >= max_active_tasks)
+            || self
+                .max_concurrency
+                .is_some_and(|max_concurrency| instances >= max_concurrency)
+            || resource_pool.is_some_and(|resource_pool| {
+                used_slots + self.pool_slots.min(resource_pool.slots) > resource_pool.slots
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use chrono::Utc;
+
+    use super::QueuedTask;
+    use crate::resource_pool::ResourcePool;
+
+    fn queued_task(pipeline_name: &str, task_name: &str) -> QueuedTask {
+        QueuedTask {
+            task_id: 0,
+            run_id: 0,
+            pipeline_name: pipeline_name.into(),
+            scheduled_date_for_run: Utc::now(),
+            attempt: 1,
+            not_before: None,
+            task_name: task_name.into(),
+            max_concurrency: None,
+            max_active_tasks: None,
+            pool: None,
+            pool_slots: 1,
+            priority_weight: 1,
+            queue: "default".into(),
+        }
+    }
+
+    #[test]
+    fn test_is_saturated() {
+        let running = [
+            queued_task("etl", "load"),
+            queued_task("etl", "extract"),
+            queued_task("other", "load"),
+        ];
+
+        let mut load = queued_task("etl", "load");
+        assert!(!load.is_saturated(&running, &BTreeMap::new()));
+
+        load.max_concurrency = Some(2);
+        assert!(!load.is_saturated(&running, &BTreeMap::new()));
+        load.max_concurrency = Some(1);
+        assert!(load.is_saturated(&running, &BTreeMap::new()));
+
+        load.max_concurrency = None;
+        load.max_active_tasks = Some(3);
+        assert!(!load.is_saturated(&running, &BTreeMap::new()));
+        load.max_active_tasks = Some(2);
+        assert!(load.is_saturated(&running, &BTreeMap::new()));
+        assert!(!queued_task("other", "load").is_saturated(&running, &BTreeMap::new()));
+
+        let resource_pools = BTreeMap::from([(
+            "db".to_string(),
+            ResourcePool {
+                slots: 3,
+                description: "".into(),
+            },
+        )]);
+        let mut running = running.map(|mut queued_task| {
+            queued_task.pool = Some("db".into());
+            queued_task
+        });
+        running[0].pool_slots = 2;
+        running[1].pool = None;
+
+        let mut load = queued_task("other", "extract");
+        load.pool = Some("db".into());
+        assert!(load.is_saturated(&running, &resource_pools));
+        assert!(!load.is_saturated(&running[1..], &resource_pools));
+        load.pool_slots = 10;
+        assert!(!load.is_saturated(&[], &resource_pools));
+        assert!(load.is_saturated(&running[1..], &resource_pools));
+        load.pool = Some("undefined".into());
+        assert!(!load.is_saturated(&running, &resource_pools));
+    }
+
+    #[test]
+    fn test_deserialize_defaults() {
+        let mut queued_task = serde_json::to_value(queued_task("etl", "load")).unwrap();
+        let queued_task_object = queued_task.as_object_mut().unwrap();
+        queued_task_object.remove("pool_slots");
+        queued_task_object.remove("queue");
+
+        // tasks queued before these fields existed take the defaults of `TaskOptions`
+        let queued_task: QueuedTask = serde_json::from_value(queued_task).unwrap();
+        assert_eq!(queued_task.pool_slots, 1);
+        assert_eq!(queued_task.queue, "default");
+    }
+
+    #[test]
+    fn test_is_served_by() {
+        let mut load = queued_task("etl", "load");
+        assert!(load.is_served_by(None));
+        assert!(load.is_served_by(Some(&["default".into()])));
+        assert!(!load.is_served_by(Some(&[])));
+
+        load.queue = "gpu".into();
+        assert!(!load.is_served_by(Some(&["default".into()])));
+        assert!(load.is_served_by(Some(&["default".into(), "gpu".into()])));
+    }
 }
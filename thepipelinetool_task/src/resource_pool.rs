@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+/// A named number of slots shared by the tasks that use it, across pipelines.
+/// See `TaskOptions::pool`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourcePool {
+    pub slots: usize,
+
+    #[serde(default)]
+    pub description: String,
+}
@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub enum RetryPolicy {
+    /// Waits `retry_delay` before every retry.
+    #[default]
+    Fixed,
+
+    /// Waits `retry_delay * multiplier^(attempt - 1)` before retrying a failed attempt, but never
+    /// longer than `max_delay`.
+    Exponential {
+        multiplier: f64,
+        max_delay: Duration,
+    },
+}
+
+impl RetryPolicy {
+    pub fn get_delay(&self, retry_delay: Duration, attempt: usize) -> Duration {
+        match self {
+            RetryPolicy::Fixed => retry_delay,
+            RetryPolicy::Exponential {
+                multiplier,
+                max_delay,
+            } => {
+                let exponent = i32::try_from(attempt.saturating_sub(1)).unwrap_or(i32::MAX);
+                let delay = retry_delay.as_secs_f64() * multiplier.powi(exponent);
+
+                if !delay.is_finite() || delay >= max_delay.as_secs_f64() {
+                    *max_delay
+                } else {
+                    Duration::from_secs_f64(delay.max(0.0))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::RetryPolicy;
+
+    #[test]
+    fn test_get_delay() {
+        let retry_delay = Duration::from_secs(2);
+        assert_eq!(RetryPolicy::Fixed.get_delay(retry_delay, 5), retry_delay);
+
+        let exponential = RetryPolicy::Exponential {
+            multiplier: 3.0,
+            max_delay: Duration::from_secs(60),
+        };
+        assert_eq!(
+            exponential.get_delay(retry_delay, 1),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            exponential.get_delay(retry_delay, 2),
+            Duration::from_secs(6)
+        );
+        assert_eq!(
+            exponential.get_delay(retry_delay, 3),
+            Duration::from_secs(18)
+        );
+        assert_eq!(
+            exponential.get_delay(retry_delay, 4),
+            Duration::from_secs(54)
+        );
+        assert_eq!(
+            exponential.get_delay(retry_delay, 5),
+            Duration::from_secs(60)
+        );
+        assert_eq!(
+            exponential.get_delay(retry_delay, usize::MAX),
+            Duration::from_secs(60)
+        );
+    }
+}
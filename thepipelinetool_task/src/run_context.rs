@@ -0,0 +1,118 @@
+use std::env;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// What a task is running for. It is passed to tasks as environment variables named after its
+/// fields, and to template expressions as variables.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunContext {
+    pub pipeline_name: String,
+    pub run_id: usize,
+    pub task_name: String,
+    pub task_id: usize,
+    pub attempt: usize,
+
+    /// The logical date of the run.
+    pub scheduled_date: DateTime<Utc>,
+
+    /// The window of data the run is meant to process.
+    pub data_interval_start: DateTime<Utc>,
+    pub data_interval_end: DateTime<Utc>,
+
+    /// The params the run was triggered with.
+    pub params: Value,
+}
+
+fn to_rfc3339(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+impl RunContext {
+    /// The environment variables of a task, with dates in RFC 3339 and params as JSON.
+    pub fn to_env(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("pipeline_name", self.pipeline_name.clone()),
+            ("run_id", self.run_id.to_string()),
+            ("task_name", self.task_name.clone()),
+            ("task_id", self.task_id.to_string()),
+            ("attempt", self.attempt.to_string()),
+            ("scheduled_date", to_rfc3339(&self.scheduled_date)),
+            ("data_interval_start", to_rfc3339(&self.data_interval_start)),
+            ("data_interval_end", to_rfc3339(&self.data_interval_end)),
+            ("params", self.params.to_string()),
+        ]
+    }
+
+    /// Reads the context of the task being run from the environment variables set by the runner.
+    pub fn from_env() -> Result<Self> {
+        let var = |name: &str| {
+            env::var(name).with_context(|| format!("missing run context variable '{name}'"))
+        };
+        let number = |name: &str| -> Result<usize> {
+            var(name)?
+                .parse()
+                .with_context(|| format!("invalid run context variable '{name}'"))
+        };
+        let date = |name: &str| -> Result<DateTime<Utc>> {
+            Ok(DateTime::parse_from_rfc3339(&var(name)?)
+                .with_context(|| format!("invalid run context variable '{name}'"))?
+                .into())
+        };
+
+        Ok(Self {
+            pipeline_name: var("pipeline_name")?,
+            run_id: number("run_id")?,
+            task_name: var("task_name")?,
+            task_id: number("task_id")?,
+            attempt: number("attempt")?,
+            scheduled_date: date("scheduled_date")?,
+            data_interval_start: date("data_interval_start")?,
+            data_interval_end: date("data_interval_end")?,
+            params: serde_json::from_str(&var("params")?)
+                .context("invalid run context variable 'params'")?,
+        })
+    }
+
+    /// The variables of template expressions, by name.
+    pub fn to_template_context(&self) -> Map<String, Value> {
+        match serde_json::to_value(self) {
+            Ok(Value::Object(map)) => map,
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    use super::RunContext;
+
+    #[test]
+    fn test_env() {
+        let scheduled_date = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let run_context = RunContext {
+            pipeline_name: "etl".into(),
+            run_id: 3,
+            task_name: "load".into(),
+            task_id: 1,
+            attempt: 2,
+            scheduled_date,
+            data_interval_start: scheduled_date - chrono::Duration::days(1),
+            data_interval_end: scheduled_date,
+            params: json!({ "full_refresh": true }),
+        };
+        for (name, value) in run_context.to_env() {
+            std::env::set_var(name, value);
+        }
+        assert_eq!(RunContext::from_env().unwrap(), run_context);
+
+        let template_context = run_context.to_template_context();
+        assert_eq!(template_context["scheduled_date"], "2024-03-01T00:00:00Z");
+        assert_eq!(template_context["params"]["full_refresh"], true);
+    }
+}
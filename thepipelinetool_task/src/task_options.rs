@@ -1,10 +1,11 @@
 use std::time::Duration;
 
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
-use crate::trigger_rule::TriggerRule;
+use crate::{retry_policy::RetryPolicy, trigger_rule::TriggerRule, weight_rule::WeightRule};
 
-#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct TaskOptions {
     #[serde(default)]
     pub max_attempts: usize,
@@ -12,24 +13,136 @@ pub struct TaskOptions {
     #[serde(default)]
     pub retry_delay: Duration,
 
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
+
+    /// Up to this much random time is added to every retry delay.
+    #[serde(default)]
+    pub retry_jitter: Duration,
+
     #[serde(default)]
     pub timeout: Option<Duration>,
 
+    #[serde(default = "default_timeout_grace_period")]
+    pub timeout_grace_period: Duration,
+
     #[serde(default)]
     pub is_sensor: bool,
 
+    /// Exit codes other than 0 that count as success.
+    #[serde(default)]
+    pub success_exit_codes: Vec<i32>,
+
+    /// When set, only failures with one of these exit codes are retried.
+    #[serde(default)]
+    pub retry_exit_codes: Option<Vec<i32>>,
+
+    /// Exit code that marks the task and everything downstream of it as skipped.
+    #[serde(default)]
+    pub skip_exit_code: Option<i32>,
+
     #[serde(default)]
     pub trigger_rule: TriggerRule,
+
+    /// The task only runs when this condition holds, otherwise it's skipped. The tasks it reads
+    /// must be upstream of the task, or the task fails. See [`crate::condition`] for the syntax.
+    #[serde(default)]
+    pub when: Option<String>,
+
+    /// How many instances of the task can run at once, across all runs of the pipeline.
+    #[serde(default)]
+    pub max_concurrency: Option<usize>,
+
+    /// The resource pool the task takes slots from while it runs. Pools that aren't defined
+    /// don't limit anything.
+    #[serde(default)]
+    pub pool: Option<String>,
+
+    /// How many slots of its pool the task takes, at most the size of the pool.
+    #[serde(default = "default_pool_slots")]
+    pub pool_slots: usize,
+
+    /// Queued tasks with a higher priority weight are popped first, then the ones closer to the
+    /// start of their run.
+    #[serde(default = "default_priority_weight")]
+    pub priority_weight: usize,
+
+    /// How `priority_weight` adds up with the weights of related tasks.
+    #[serde(default)]
+    pub weight_rule: WeightRule,
+
+    /// Only workers that serve this queue pop the task, e.g. the ones with database credentials.
+    /// Local runs ignore it.
+    #[serde(default = "default_queue")]
+    pub queue: String,
+}
+
+pub const DEFAULT_QUEUE: &str = "default";
+
+pub(crate) fn default_queue() -> String {
+    DEFAULT_QUEUE.to_string()
+}
+
+fn default_priority_weight() -> usize {
+    1
+}
+
+pub(crate) fn default_pool_slots() -> usize {
+    1
+}
+
+fn default_timeout_grace_period() -> Duration {
+    Duration::from_secs(10)
 }
 
 impl Default for TaskOptions {
     fn default() -> Self {
         Self {
             is_sensor: false,
+            success_exit_codes: vec![],
+            retry_exit_codes: None,
+            skip_exit_code: None,
             retry_delay: Duration::ZERO,
+            retry_policy: RetryPolicy::Fixed,
+            retry_jitter: Duration::ZERO,
             timeout: None,
+            timeout_grace_period: default_timeout_grace_period(),
             max_attempts: 1,
             trigger_rule: TriggerRule::AllDone,
+            when: None,
+            max_concurrency: None,
+            pool: None,
+            pool_slots: default_pool_slots(),
+            priority_weight: default_priority_weight(),
+            weight_rule: WeightRule::Absolute,
+            queue: default_queue(),
+        }
+    }
+}
+
+impl TaskOptions {
+    pub fn is_success_exit_code(&self, exit_code: i32) -> bool {
+        exit_code == 0 || self.success_exit_codes.contains(&exit_code)
+    }
+
+    pub fn is_skip_exit_code(&self, exit_code: Option<i32>) -> bool {
+        exit_code.is_some() && exit_code == self.skip_exit_code
+    }
+
+    /// Failures without an exit code, like timeouts, are always retryable.
+    pub fn is_retryable_exit_code(&self, exit_code: Option<i32>) -> bool {
+        match (&self.retry_exit_codes, exit_code) {
+            (Some(retry_exit_codes), Some(exit_code)) => retry_exit_codes.contains(&exit_code),
+            _ => true,
+        }
+    }
+
+    /// How long to wait before retrying the given failed attempt.
+    pub fn get_retry_delay(&self, attempt: usize) -> Duration {
+        let delay = self.retry_policy.get_delay(self.retry_delay, attempt);
+        if self.retry_jitter.is_zero() {
+            return delay;
         }
+        delay.saturating_add(rand::thread_rng().gen_range(Duration::ZERO..=self.retry_jitter))
     }
 }
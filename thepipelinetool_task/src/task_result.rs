@@ -2,6 +2,8 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::task_options::TaskOptions;
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct TaskResult {
     pub task_id: usize,
@@ -20,12 +22,16 @@ pub struct TaskResult {
     pub is_branch: bool,
     pub is_sensor: bool,
     pub exit_code: Option<i32>,
+    #[serde(default)]
+    pub skipped: bool,
 }
 
 impl TaskResult {
-    pub fn needs_retry(&self) -> bool {
+    pub fn needs_retry(&self, options: &TaskOptions) -> bool {
         !self.premature_failure
             && !self.success
+            && !self.skipped
+            && options.is_retryable_exit_code(self.exit_code)
             && (self.is_sensor || self.attempt < self.max_attempts)
     }
 
@@ -58,6 +64,37 @@ impl TaskResult {
             is_branch,
             is_sensor,
             exit_code: None,
+            skipped: false,
+        }
+    }
+
+    pub fn condition_not_met(
+        task_id: usize,
+        attempt: usize,
+        max_attempts: usize,
+        name: String,
+        function_name: String,
+        is_branch: bool,
+        is_sensor: bool,
+    ) -> Self {
+        Self {
+            task_id,
+            result: Value::Null,
+            attempt,
+            max_attempts,
+            name,
+            function: function_name,
+            success: false,
+            resolved_args_str: "".into(),
+            started: None,
+            ended: None,
+            elapsed: 0,
+            premature_failure: false,
+            premature_failure_error_str: "".into(),
+            is_branch,
+            is_sensor,
+            exit_code: None,
+            skipped: true,
         }
     }
 
@@ -79,6 +116,9 @@ impl TaskResult {
         );
         println!("------Log------\n{}\n------------------", log);
         println!("success:\t{}", self.success);
+        if self.skipped {
+            println!("skipped:\ttrue");
+        }
         println!(
             "started:\t{}",
             match self.started {
@@ -8,4 +8,5 @@ pub enum TaskStatus {
     Success,
     Failure,
     Skipped,
+    Cancelled,
 }
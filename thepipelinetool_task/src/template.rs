@@ -0,0 +1,401 @@
+//! Template expressions like `{{ extract.files[0].name | default: "none" | upper }}`, used in the
+//! scripts of YAML tasks and in `TaskOptions::when`.
+//!
+//! An expression starts with a name, which is an upstream task or one of [`RUN_CONTEXT_NAMES`],
+//! followed by keys (`.key`, `.0`, `[0]`, `["key"]`) and filters (`| name` or
+//! `| name: arg, arg`). Arguments are numbers, quoted strings, `true`, `false` and `null`.
+//!
+//! Filters:
+//! - `default: value` replaces a missing or `null` value
+//! - `json` and `tojson` serialize the value as a JSON string
+//! - `upper`, `lower` and `trim` change a string
+//! - `join: separator` joins the items of an array, with `,` by default
+//! - `length`, `first` and `last` work on arrays, objects and strings
+//! - `date: format` formats an RFC 3339 date or a unix timestamp, with `%Y-%m-%d` by default
+//! - `raw` leaves the value as is, and is read by `bash_operator` to skip shell quoting
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+/// Names available to every expression, from the [`RunContext`](crate::run_context::RunContext)
+/// of the task.
+pub const RUN_CONTEXT_NAMES: [&str; 9] = [
+    "pipeline_name",
+    "run_id",
+    "task_name",
+    "task_id",
+    "attempt",
+    "scheduled_date",
+    "data_interval_start",
+    "data_interval_end",
+    "params",
+];
+
+const FILTERS: [&str; 12] = [
+    "default", "json", "tojson", "upper", "lower", "trim", "join", "length", "first", "last",
+    "date", "raw",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Key {
+    Field(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Filter {
+    name: String,
+    args: Vec<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    /// The upstream task or run context name the expression starts with.
+    pub root: String,
+    keys: Vec<Key>,
+    filters: Vec<Filter>,
+}
+
+/// The expression inside a `{{ ... }}` placeholder.
+pub fn get_placeholder_expression(placeholder: &str) -> Option<&str> {
+    placeholder.strip_prefix("{{")?.strip_suffix("}}")
+}
+
+impl Expression {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let mut parser = Parser {
+            chars: expression.chars().collect(),
+            position: 0,
+        };
+        let parsed = parser
+            .expression()
+            .map_err(|err| anyhow!("{err} in '{expression}'"))?;
+        parser.skip_whitespace();
+        if parser.position < parser.chars.len() {
+            bail!("unexpected '{}' in '{expression}'", parser.rest());
+        }
+        Ok(parsed)
+    }
+
+    /// Evaluates the expression, failing when it refers to something missing and has no default.
+    pub fn evaluate(&self, context: &Value) -> Result<Value> {
+        self.apply(context)?
+            .ok_or_else(|| anyhow!("'{}' is not defined", self.path()))
+    }
+
+    /// Evaluates the expression, with `null` for anything missing.
+    pub fn evaluate_or_null(&self, context: &Value) -> Result<Value> {
+        Ok(self.apply(context)?.unwrap_or_default())
+    }
+
+    fn path(&self) -> String {
+        self.keys
+            .iter()
+            .fold(self.root.clone(), |path, key| match key {
+                Key::Field(field) => format!("{path}.{field}"),
+                Key::Index(index) => format!("{path}[{index}]"),
+            })
+    }
+
+    fn apply(&self, context: &Value) -> Result<Option<Value>> {
+        let mut value = context.get(&self.root);
+        for key in &self.keys {
+            value = value.and_then(|value| match (key, value) {
+                (Key::Field(field), Value::Object(map)) => map.get(field),
+                (Key::Field(field), Value::Array(array)) => {
+                    field.parse::<usize>().ok().and_then(|i| array.get(i))
+                }
+                (Key::Index(index), Value::Array(array)) => array.get(*index),
+                (Key::Index(index), Value::Object(map)) => map.get(&index.to_string()),
+                _ => None,
+            });
+        }
+
+        let mut value = value.cloned();
+        for filter in &self.filters {
+            value = match (filter.name.as_str(), value) {
+                ("default", None | Some(Value::Null)) => {
+                    Some(filter.args.first().cloned().unwrap_or_default())
+                }
+                (_, None) => None,
+                (_, Some(value)) => Some(apply_filter(filter, value)?),
+            };
+        }
+        Ok(value)
+    }
+}
+
+fn to_text(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn apply_filter(filter: &Filter, value: Value) -> Result<Value> {
+    let arg = |i: usize| filter.args.get(i).map(to_text);
+
+    Ok(match filter.name.as_str() {
+        "default" | "raw" => value,
+        "json" | "tojson" => Value::String(value.to_string()),
+        "upper" => Value::String(to_text(&value).to_uppercase()),
+        "lower" => Value::String(to_text(&value).to_lowercase()),
+        "trim" => Value::String(to_text(&value).trim().to_string()),
+        "join" => match &value {
+            Value::Array(array) => Value::String(
+                array
+                    .iter()
+                    .map(to_text)
+                    .collect::<Vec<String>>()
+                    .join(&arg(0).unwrap_or(",".into())),
+            ),
+            _ => bail!("join needs an array, not {value}"),
+        },
+        "length" => match &value {
+            Value::Array(array) => array.len().into(),
+            Value::Object(map) => map.len().into(),
+            Value::String(string) => string.chars().count().into(),
+            _ => bail!("length needs an array, object or string, not {value}"),
+        },
+        "first" | "last" => {
+            let first = filter.name == "first";
+            match value {
+                Value::Array(mut array) if first && !array.is_empty() => array.remove(0),
+                Value::Array(mut array) => array.pop().unwrap_or_default(),
+                Value::String(string) => {
+                    let c = if first {
+                        string.chars().next()
+                    } else {
+                        string.chars().last()
+                    };
+                    c.map(|c| Value::String(c.to_string())).unwrap_or_default()
+                }
+                value => bail!("{} needs an array or string, not {value}", filter.name),
+            }
+        }
+        "date" => {
+            let date: DateTime<Utc> = match &value {
+                Value::String(string) => DateTime::parse_from_rfc3339(string)
+                    .map_err(|err| anyhow!("can't read date '{string}': {err}"))?
+                    .into(),
+                Value::Number(number) => number
+                    .as_i64()
+                    .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))
+                    .ok_or_else(|| anyhow!("can't read timestamp {number}"))?,
+                _ => bail!("date needs a date or timestamp, not {value}"),
+            };
+            Value::String(
+                date.format(&arg(0).unwrap_or("%Y-%m-%d".into()))
+                    .to_string(),
+            )
+        }
+        name => bail!("unknown filter '{name}'"),
+    })
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn rest(&self) -> String {
+        self.chars[self.position..].iter().collect()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    fn next_if(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn name(&mut self) -> Result<String> {
+        self.skip_whitespace();
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || "_-/".contains(c))
+        {
+            self.position += 1;
+        }
+        if start == self.position {
+            bail!("expected a name");
+        }
+        Ok(self.chars[start..self.position].iter().collect())
+    }
+
+    fn literal(&mut self) -> Result<Value> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(quote @ ('"' | '\'')) => {
+                self.position += 1;
+                let start = self.position;
+                while self.peek().is_some_and(|c| c != quote) {
+                    self.position += 1;
+                }
+                if self.peek().is_none() {
+                    bail!("unclosed string");
+                }
+                let string: String = self.chars[start..self.position].iter().collect();
+                self.position += 1;
+                Ok(Value::String(string))
+            }
+            _ => {
+                let start = self.position;
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_alphanumeric() || "-+.".contains(c))
+                {
+                    self.position += 1;
+                }
+                let word: String = self.chars[start..self.position].iter().collect();
+                serde_json::from_str(&word).map_err(|_| anyhow!("expected a value, not '{word}'"))
+            }
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expression> {
+        let root = self.name()?;
+
+        let mut keys = vec![];
+        loop {
+            if self.next_if('.') {
+                let field = self.name()?;
+                keys.push(match field.parse::<usize>() {
+                    Ok(index) => Key::Index(index),
+                    Err(_) => Key::Field(field),
+                });
+            } else if self.next_if('[') {
+                keys.push(match self.literal()? {
+                    Value::String(field) => Key::Field(field),
+                    Value::Number(index) if index.is_u64() => {
+                        Key::Index(index.as_u64().unwrap() as usize)
+                    }
+                    value => bail!("can't use {value} as a key"),
+                });
+                if !self.next_if(']') {
+                    bail!("missing ']'");
+                }
+            } else {
+                break;
+            }
+        }
+
+        let mut filters = vec![];
+        while self.next_if('|') {
+            let name = self.name()?;
+            if !FILTERS.contains(&name.as_str()) {
+                bail!("unknown filter '{name}'");
+            }
+            let mut args = vec![];
+            if self.next_if(':') {
+                args.push(self.literal()?);
+                while self.next_if(',') {
+                    args.push(self.literal()?);
+                }
+            }
+            filters.push(Filter { name, args });
+        }
+
+        Ok(Expression {
+            root,
+            keys,
+            filters,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::Expression;
+
+    #[test]
+    fn test_evaluate() {
+        let context = json!({
+            "extract": {
+                "files": [{ "name": "a.csv", "size": 10 }, { "name": "b.csv" }],
+                "tags": ["x", "y"],
+                "empty": null,
+            },
+            "team/notify": "sent",
+            "run_id": 7,
+            "scheduled_date": "2024-03-01T12:30:00+00:00",
+            "params": { "full_refresh": true },
+        });
+        for (expression, expected) in [
+            ("extract.files[0].name", json!("a.csv")),
+            (" extract.files.1.name ", json!("b.csv")),
+            ("extract[\"tags\"][1]", json!("y")),
+            ("extract.files[1].size | default: 0", json!(0)),
+            ("extract.empty | default: 'none' | upper", json!("NONE")),
+            ("extract.tags | join", json!("x,y")),
+            ("extract.tags | join: ' | '", json!("x | y")),
+            ("extract.files | length", json!(2)),
+            ("extract.files | last | json", json!("{\"name\":\"b.csv\"}")),
+            ("extract.tags | tojson", json!("[\"x\",\"y\"]")),
+            ("extract.tags | first | upper", json!("X")),
+            ("team/notify | lower", json!("sent")),
+            ("run_id", json!(7)),
+            ("scheduled_date | date", json!("2024-03-01")),
+            ("scheduled_date | date: '%H:%M'", json!("12:30")),
+            ("run_id | date: '%Y'", json!("1970")),
+            ("params.full_refresh", json!(true)),
+            ("params.missing | default: false", json!(false)),
+            ("extract.files | raw", context["extract"]["files"].clone()),
+        ] {
+            assert_eq!(
+                Expression::parse(expression)
+                    .unwrap()
+                    .evaluate(&context)
+                    .unwrap(),
+                expected,
+                "{expression}"
+            );
+        }
+
+        for expression in [
+            "extract.missing",
+            "missing",
+            "extract.files[5] | upper",
+            "extract.tags | length | join",
+            "run_id | trim | date",
+        ] {
+            let expression = Expression::parse(expression).unwrap();
+            assert!(expression.evaluate(&context).is_err(), "{expression:?}");
+        }
+        assert_eq!(
+            Expression::parse("extract.missing | upper")
+                .unwrap()
+                .evaluate_or_null(&context)
+                .unwrap(),
+            json!(null)
+        );
+
+        for expression in [
+            "",
+            "extract.",
+            "extract[0",
+            "extract | nope",
+            "extract | default: 'x",
+            "extract other",
+        ] {
+            assert!(Expression::parse(expression).is_err(), "{expression}");
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::task_status::TaskStatus;
+
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, Default)]
 pub enum TriggerRule {
     #[default]
@@ -11,4 +13,288 @@ pub enum TriggerRule {
 
     AnyFailed,
     AllFailed,
+
+    /// All upstream tasks succeeded or were skipped.
+    NoneFailed,
+    /// All upstream tasks succeeded or failed.
+    NoneSkipped,
+    AllSkipped,
+    /// Runs as soon as the tasks it takes results from are done, regardless of their status.
+    Always,
+    /// All upstream tasks succeeded or were skipped, and at least one succeeded.
+    NoneFailedMinOneSuccess,
+}
+
+impl TriggerRule {
+    /// Whether a task with this rule can run, given the statuses of all of its upstream tasks.
+    ///
+    /// A task without upstream tasks has nothing to wait for, so every rule is satisfied.
+    pub fn is_satisfied(&self, upstream_statuses: &[TaskStatus]) -> bool {
+        if upstream_statuses.is_empty() {
+            return true;
+        }
+        let all = |f: fn(&TaskStatus) -> bool| upstream_statuses.iter().all(f);
+        let any = |f: fn(&TaskStatus) -> bool| upstream_statuses.iter().any(f);
+
+        match self {
+            TriggerRule::AllDone => all(is_done),
+            TriggerRule::AnyDone => any(is_done),
+            TriggerRule::AllSuccess => all(|s| *s == TaskStatus::Success),
+            TriggerRule::AnySuccess => any(|s| *s == TaskStatus::Success),
+            TriggerRule::AnyFailed => any(|s| *s == TaskStatus::Failure),
+            TriggerRule::AllFailed => all(|s| *s == TaskStatus::Failure),
+            TriggerRule::NoneFailed => {
+                all(|s| matches!(s, TaskStatus::Success | TaskStatus::Skipped))
+            }
+            TriggerRule::NoneSkipped => {
+                all(|s| matches!(s, TaskStatus::Success | TaskStatus::Failure))
+            }
+            TriggerRule::AllSkipped => all(|s| *s == TaskStatus::Skipped),
+            TriggerRule::Always => true,
+            TriggerRule::NoneFailedMinOneSuccess => {
+                all(|s| matches!(s, TaskStatus::Success | TaskStatus::Skipped))
+                    && any(|s| *s == TaskStatus::Success)
+            }
+        }
+    }
+
+    /// Whether a task is skipped along with its upstream tasks: when its rule can't be satisfied
+    /// anymore, or when all of its upstream tasks were skipped and the rule doesn't tolerate that.
+    /// Otherwise it waits for the rest of its upstream tasks.
+    pub fn skips_with_upstream(&self, upstream_statuses: &[TaskStatus]) -> bool {
+        let tolerates_skipped = matches!(
+            self,
+            TriggerRule::NoneFailed
+                | TriggerRule::AllSkipped
+                | TriggerRule::Always
+                | TriggerRule::NoneFailedMinOneSuccess
+        );
+        !self.can_be_satisfied(upstream_statuses)
+            || !tolerates_skipped && upstream_statuses.iter().all(|s| *s == TaskStatus::Skipped)
+    }
+
+    /// Whether the rule is satisfied now, or can still be once the upstream tasks that aren't
+    /// done are.
+    pub fn can_be_satisfied(&self, upstream_statuses: &[TaskStatus]) -> bool {
+        // every rule is satisfied by the remaining tasks all ending the same way, if at all
+        [
+            TaskStatus::Success,
+            TaskStatus::Failure,
+            TaskStatus::Skipped,
+            TaskStatus::Cancelled,
+        ]
+        .into_iter()
+        .any(|final_status| {
+            let final_statuses: Vec<TaskStatus> = upstream_statuses
+                .iter()
+                .map(|status| {
+                    if is_done(status) {
+                        status.clone()
+                    } else {
+                        final_status.clone()
+                    }
+                })
+                .collect();
+            self.is_satisfied(&final_statuses)
+        })
+    }
+}
+
+fn is_done(status: &TaskStatus) -> bool {
+    !matches!(
+        status,
+        TaskStatus::Pending | TaskStatus::Running | TaskStatus::RetryPending
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::task_status::TaskStatus::{self, *};
+
+    use super::TriggerRule;
+
+    const ALL_TASK_STATUSES: [TaskStatus; 7] = [
+        Pending,
+        Running,
+        RetryPending,
+        Success,
+        Failure,
+        Skipped,
+        Cancelled,
+    ];
+
+    const ALL_TRIGGER_RULES: [TriggerRule; 11] = [
+        TriggerRule::AllDone,
+        TriggerRule::AnyDone,
+        TriggerRule::AllSuccess,
+        TriggerRule::AnySuccess,
+        TriggerRule::AnyFailed,
+        TriggerRule::AllFailed,
+        TriggerRule::NoneFailed,
+        TriggerRule::NoneSkipped,
+        TriggerRule::AllSkipped,
+        TriggerRule::Always,
+        TriggerRule::NoneFailedMinOneSuccess,
+    ];
+
+    /// Every combination of statuses for up to `max_len` upstream tasks.
+    fn upstream_combinations(max_len: usize) -> Vec<Vec<TaskStatus>> {
+        let mut combinations = vec![vec![]];
+        let mut previous = vec![vec![]];
+        for _ in 0..max_len {
+            previous = previous
+                .iter()
+                .flat_map(|combination: &Vec<TaskStatus>| {
+                    ALL_TASK_STATUSES.iter().map(move |status| {
+                        let mut combination = combination.clone();
+                        combination.push(status.clone());
+                        combination
+                    })
+                })
+                .collect();
+            combinations.extend(previous.clone());
+        }
+        combinations
+    }
+
+    #[test]
+    fn test_is_satisfied() {
+        let cases: [(TriggerRule, &[TaskStatus], bool); 24] = [
+            (TriggerRule::AllSuccess, &[Success, Success], true),
+            (TriggerRule::AllSuccess, &[Success, Pending], false),
+            (TriggerRule::AllSuccess, &[Success, Skipped], false),
+            (TriggerRule::AllSuccess, &[Failure], false),
+            (TriggerRule::AllDone, &[Success, Failure, Skipped], true),
+            (TriggerRule::AllDone, &[Success, RetryPending], false),
+            (TriggerRule::AnyDone, &[Running, Cancelled], true),
+            (TriggerRule::AnyDone, &[Running, Pending], false),
+            (TriggerRule::AnySuccess, &[Failure, Success], true),
+            (TriggerRule::AnySuccess, &[Failure, Skipped], false),
+            (TriggerRule::AnyFailed, &[Pending, Failure], true),
+            (TriggerRule::AnyFailed, &[Success, Skipped], false),
+            (TriggerRule::AllFailed, &[Failure, Failure], true),
+            (TriggerRule::AllFailed, &[Failure, Skipped], false),
+            (TriggerRule::NoneFailed, &[Success, Skipped], true),
+            (TriggerRule::NoneFailed, &[Success, Failure], false),
+            (TriggerRule::NoneSkipped, &[Success, Failure], true),
+            (TriggerRule::NoneSkipped, &[Success, Skipped], false),
+            (TriggerRule::AllSkipped, &[Skipped, Skipped], true),
+            (TriggerRule::AllSkipped, &[Skipped, Success], false),
+            (TriggerRule::Always, &[Pending, Running], true),
+            (
+                TriggerRule::NoneFailedMinOneSuccess,
+                &[Skipped, Success],
+                true,
+            ),
+            (
+                TriggerRule::NoneFailedMinOneSuccess,
+                &[Skipped, Skipped],
+                false,
+            ),
+            (
+                TriggerRule::NoneFailedMinOneSuccess,
+                &[Success, Failure],
+                false,
+            ),
+        ];
+        for (trigger_rule, upstream_statuses, expected) in cases {
+            assert_eq!(
+                trigger_rule.is_satisfied(upstream_statuses),
+                expected,
+                "{trigger_rule:?} with upstream {upstream_statuses:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_can_be_satisfied() {
+        let cases: [(TriggerRule, &[TaskStatus], bool); 10] = [
+            (TriggerRule::AnySuccess, &[Skipped, Pending], true),
+            (TriggerRule::AnySuccess, &[Skipped, Failure], false),
+            (TriggerRule::AnyDone, &[Skipped, Pending], true),
+            (TriggerRule::AllDone, &[Skipped, Running], true),
+            (TriggerRule::AllSuccess, &[Skipped, Pending], false),
+            (TriggerRule::AnyFailed, &[Skipped, RetryPending], true),
+            (TriggerRule::AllFailed, &[Skipped, Pending], false),
+            (TriggerRule::NoneFailed, &[Skipped, Pending], true),
+            (TriggerRule::NoneSkipped, &[Skipped, Pending], false),
+            (TriggerRule::AllSkipped, &[Skipped, Pending], true),
+        ];
+        for (trigger_rule, upstream_statuses, expected) in cases {
+            assert_eq!(
+                trigger_rule.can_be_satisfied(upstream_statuses),
+                expected,
+                "{trigger_rule:?} with upstream {upstream_statuses:?}"
+            );
+        }
+
+        // a rule that is satisfied can be
+        for upstream_statuses in upstream_combinations(3) {
+            for trigger_rule in ALL_TRIGGER_RULES {
+                assert!(
+                    !trigger_rule.is_satisfied(&upstream_statuses)
+                        || trigger_rule.can_be_satisfied(&upstream_statuses)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_skips_with_upstream() {
+        let cases: [(TriggerRule, &[TaskStatus], bool); 7] = [
+            (TriggerRule::AllDone, &[Skipped], true),
+            (TriggerRule::AllDone, &[Skipped, Pending], false),
+            (TriggerRule::AnySuccess, &[Skipped, Running], false),
+            (TriggerRule::AnySuccess, &[Skipped, Failure], true),
+            (TriggerRule::AllSuccess, &[Skipped, Pending], true),
+            (TriggerRule::NoneFailed, &[Skipped, Skipped], false),
+            (
+                TriggerRule::NoneFailedMinOneSuccess,
+                &[Skipped, Skipped],
+                true,
+            ),
+        ];
+        for (trigger_rule, upstream_statuses, expected) in cases {
+            assert_eq!(
+                trigger_rule.skips_with_upstream(upstream_statuses),
+                expected,
+                "{trigger_rule:?} with upstream {upstream_statuses:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_satisfied_exhaustive() {
+        for upstream_statuses in upstream_combinations(3) {
+            let count =
+                |status: TaskStatus| upstream_statuses.iter().filter(|s| **s == status).count();
+            let total = upstream_statuses.len();
+            let (success, failure, skipped) = (count(Success), count(Failure), count(Skipped));
+            let done = success + failure + skipped + count(Cancelled);
+
+            for trigger_rule in ALL_TRIGGER_RULES {
+                let expected = total == 0
+                    || match trigger_rule {
+                        TriggerRule::AllDone => done == total,
+                        TriggerRule::AnyDone => done > 0,
+                        TriggerRule::AllSuccess => success == total,
+                        TriggerRule::AnySuccess => success > 0,
+                        TriggerRule::AnyFailed => failure > 0,
+                        TriggerRule::AllFailed => failure == total,
+                        TriggerRule::NoneFailed => success + skipped == total,
+                        TriggerRule::NoneSkipped => success + failure == total,
+                        TriggerRule::AllSkipped => skipped == total,
+                        TriggerRule::Always => true,
+                        TriggerRule::NoneFailedMinOneSuccess => {
+                            success + skipped == total && success > 0
+                        }
+                    };
+                assert_eq!(
+                    trigger_rule.is_satisfied(&upstream_statuses),
+                    expected,
+                    "{trigger_rule:?} with upstream {upstream_statuses:?}"
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// How the priority weight a task is queued with is computed from `TaskOptions::priority_weight`.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub enum WeightRule {
+    /// The task's own weight.
+    #[default]
+    Absolute,
+
+    /// The task's weight plus the weights of every task downstream of it, so that tasks that
+    /// hold up more of the run go first.
+    Downstream,
+
+    /// The task's weight plus the weights of every task upstream of it, so that runs that have
+    /// started finish first.
+    Upstream,
+}
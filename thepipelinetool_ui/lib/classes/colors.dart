@@ -10,6 +10,7 @@ Color getColorByStatus(TaskStatus taskStatus) {
     TaskStatus.Running => HexColor.fromHex("#90EE90"),
     TaskStatus.Retrying => Colors.orange,
     TaskStatus.Skipped => const Color.fromARGB(255, 255, 140, 253),
+    TaskStatus.Cancelled => Colors.purple,
     TaskStatus.None => Colors.transparent,
     // (_) => Colors.transparent,
   };
@@ -9,6 +9,7 @@ enum TaskStatus {
   Success,
   Failure,
   Skipped,
+  Cancelled,
   None;
 
   // Static method to get enum from int
@@ -34,6 +35,8 @@ enum TaskStatus {
         return "Failure";
       case TaskStatus.Skipped:
         return "Skipped";
+      case TaskStatus.Cancelled:
+        return "Cancelled";
       case TaskStatus.None:
         return '';
     }
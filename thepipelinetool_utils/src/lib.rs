@@ -2,11 +2,12 @@ use std::{
     cmp::max,
     fs::File,
     io::{BufRead, BufReader, Error, Read, Write},
+    os::unix::process::{CommandExt, ExitStatusExt},
     path::Path,
     process::{self, Command, ExitStatus, Stdio},
-    sync::mpsc::channel,
+    sync::mpsc::{channel, Receiver, RecvTimeoutError},
     thread::{self, available_parallelism},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use anyhow::Result;
@@ -16,6 +17,8 @@ use serde_json::{json, Value};
 pub const UPSTREAM_TASK_ID_KEY: &str = "upstream_task_id";
 pub const UPSTREAM_TASK_RESULT_KEY: &str = "key";
 
+const CANCEL_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
 pub fn function_name_as_string<T>(_: T) -> String {
     let name = std::any::type_name::<T>();
     let name = &name.replace(['}', '{'], "");
@@ -67,17 +70,39 @@ pub fn collector(args: Value) -> Value {
     args
 }
 
+/// Why `spawn` stopped a command before it exited on its own.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Interruption {
+    TimedOut,
+    Cancelled,
+}
+
+/// Spawns `cmd` and streams its output line by line to the log handlers.
+///
+/// With a `timeout` or an `is_cancelled` check, the child is started in its own process group.
+/// When the timeout elapses, or `is_cancelled` (polled every second) returns true, the whole
+/// group is sent SIGTERM, then SIGKILL if it is still alive after `grace_period`, so that
+/// nothing the command started is left running. Otherwise the child stays in the caller's
+/// group, letting an outer timeout reach it. Returns the exit status and, if the command was
+/// stopped, the reason.
 pub fn spawn(
     mut cmd: Command,
     timeout: Option<Duration>,
+    grace_period: Duration,
+    is_cancelled: Option<Box<dyn Fn() -> bool + Send>>,
     handle_stdout_log: Box<dyn Fn(String) -> Result<()> + Send>,
     handle_stderr_log: Box<dyn Fn(String) -> Result<()> + Send>,
-) -> Result<ExitStatus> {
+) -> Result<(ExitStatus, Option<Interruption>)> {
+    let interruptible = timeout.is_some() || is_cancelled.is_some();
+    if interruptible {
+        cmd.process_group(0);
+    }
     let mut child = cmd
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()
         .expect("failed to start command");
+    let process_group_id = child.id() as i32;
 
     let mut stdout = child.stdout.take().expect("failed to take stdout");
     let mut stderr = child.stderr.take().expect("failed to take stderr");
@@ -111,13 +136,76 @@ pub fn spawn(
         }
     });
 
-    if let Some(timeout) = timeout {
-        Ok(receiver.recv_timeout(timeout)?)
-    } else {
-        Ok(receiver.recv()?)
+    if !interruptible {
+        return Ok((receiver.recv()?, None));
+    }
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        let mut wait = match deadline {
+            Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+            None => CANCEL_CHECK_INTERVAL,
+        };
+        if is_cancelled.is_some() {
+            wait = wait.min(CANCEL_CHECK_INTERVAL);
+        }
+
+        let interruption = match receiver.recv_timeout(wait) {
+            Ok(status) => return Ok((status, None)),
+            Err(RecvTimeoutError::Timeout) => {
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    Interruption::TimedOut
+                } else if is_cancelled
+                    .as_ref()
+                    .is_some_and(|is_cancelled| is_cancelled())
+                {
+                    Interruption::Cancelled
+                } else {
+                    continue;
+                }
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        return Ok((
+            terminate_process_group(process_group_id, grace_period, &receiver)?,
+            Some(interruption),
+        ));
+    }
+}
+
+fn terminate_process_group(
+    process_group_id: i32,
+    grace_period: Duration,
+    receiver: &Receiver<ExitStatus>,
+) -> Result<ExitStatus> {
+    signal_process_group(process_group_id, libc::SIGTERM);
+
+    match receiver.recv_timeout(grace_period) {
+        Ok(status) => Ok(status),
+        Err(RecvTimeoutError::Timeout) => {
+            signal_process_group(process_group_id, libc::SIGKILL);
+            Ok(receiver.recv()?)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn signal_process_group(process_group_id: i32, signal: i32) {
+    // a negative pid addresses every process in the group
+    unsafe {
+        libc::kill(-process_group_id, signal);
     }
 }
 
+/// Returns the exit code of a finished process. Processes terminated by a signal report
+/// `128 + signal`, the same way a shell does.
+pub fn get_exit_code(exit_status: &ExitStatus) -> Option<i32> {
+    exit_status
+        .code()
+        .or_else(|| exit_status.signal().map(|signal| 128 + signal))
+}
+
 pub fn run_bash_command(args: &[&str], silent: bool, parse_output_as_json: bool) -> Value {
     let mut res = json!([]);
     for args in args.split(|s| *s == "&&") {
@@ -134,7 +222,9 @@ pub fn run_bash_command(args: &[&str], silent: bool, parse_output_as_json: bool)
 
         if !output.status.success() {
             eprint!("{}", err_raw);
-            panic!("failed to run command:\n{}\n\n", args.join(" "));
+            eprintln!("failed to run command:\n{}\n", args.join(" "));
+            // exit with the command's own code so tasks can match it in their options
+            process::exit(get_exit_code(&output.status).unwrap_or(1));
         }
 
         if parse_output_as_json {
@@ -150,3 +240,95 @@ pub fn run_bash_command(args: &[&str], silent: bool, parse_output_as_json: bool)
 pub fn get_default_max_parallelism() -> usize {
     max(usize::from(available_parallelism().unwrap()) - 1, 1)
 }
+
+#[cfg(test)]
+mod test {
+    use std::{
+        env, fs,
+        path::{Path, PathBuf},
+        process::{self, Command},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::{get_exit_code, spawn, Interruption};
+
+    // runs a background `sleep` that writes its pid to the returned file, next to a foreground one
+    fn background_sleep(name: &str) -> (Command, PathBuf) {
+        let pid_file = env::temp_dir().join(format!("tpt_{name}_{}.pid", process::id()));
+        let _ = fs::remove_file(&pid_file);
+        let mut cmd = Command::new("bash");
+        cmd.args([
+            "-c",
+            &format!("sleep 30 & echo $! > {}; sleep 30", pid_file.display()),
+        ]);
+        (cmd, pid_file)
+    }
+
+    // `kill -0`, waiting a moment for the process to handle its signal
+    fn assert_killed(pid_file: &Path) {
+        let pid: libc::pid_t = fs::read_to_string(pid_file)
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        fs::remove_file(pid_file).unwrap();
+
+        let is_alive = || {
+            let exists = unsafe { libc::kill(pid, 0) } == 0;
+            // killed but not yet reaped
+            let is_zombie = fs::read_to_string(format!("/proc/{pid}/stat"))
+                .is_ok_and(|stat| stat.contains(") Z "));
+            exists && !is_zombie
+        };
+        let start = Instant::now();
+        while is_alive() && start.elapsed() < Duration::from_secs(2) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!is_alive(), "background process {pid} is still running");
+    }
+
+    #[test]
+    fn test_spawn_timeout_kills_process_group() {
+        let (cmd, pid_file) = background_sleep("timeout");
+
+        let start = Instant::now();
+        let (exit_status, interruption) = spawn(
+            cmd,
+            Some(Duration::from_millis(100)),
+            Duration::from_secs(5),
+            None,
+            Box::new(|_| Ok(())),
+            Box::new(|_| Ok(())),
+        )
+        .unwrap();
+
+        assert_eq!(interruption, Some(Interruption::TimedOut));
+        assert_eq!(get_exit_code(&exit_status), Some(128 + libc::SIGTERM));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_killed(&pid_file);
+    }
+
+    #[test]
+    fn test_spawn_cancel_kills_process_group() {
+        let (cmd, pid_file) = background_sleep("cancel");
+
+        let start = Instant::now();
+        let (exit_status, interruption) = spawn(
+            cmd,
+            Some(Duration::from_secs(30)),
+            Duration::from_secs(5),
+            Some(Box::new(move || {
+                start.elapsed() > Duration::from_millis(100)
+            })),
+            Box::new(|_| Ok(())),
+            Box::new(|_| Ok(())),
+        )
+        .unwrap();
+
+        assert_eq!(interruption, Some(Interruption::Cancelled));
+        assert_eq!(get_exit_code(&exit_status), Some(128 + libc::SIGTERM));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_killed(&pid_file);
+    }
+}